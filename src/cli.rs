@@ -1,16 +1,272 @@
-use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    paths::{SearchMode, SearchOptions},
+    settings::SearchSettings,
+};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+
+/// What to do when an added path is already saved
+#[derive(Debug, Clone, Copy)]
+pub enum Overwrite {
+    Ask,
+    Always,
+    Never,
+}
+
+/// Output format of the `list` mode
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ListFormat {
+    /// Aligned columns for humans
+    Table,
+    /// Tab separated name, path and description
+    Tsv,
+    /// Only the paths, one per line
+    Paths,
+    /// JSON array of the path entries
+    Json,
+}
+
+/// Shells supported by the `init` mode
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Formats of the `export` and `import` modes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExchangeFormat {
+    /// JSON array of the paths. Whole catalog files can be imported too
+    Json,
+    /// `[[paths]]` tables
+    Toml,
+    /// Comma separated name, path, description and tags with a header
+    Csv,
+    /// Table for wikis and READMEs
+    Markdown,
+}
+
+/// Directory jumpers whose databases the `import` mode reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Jumper {
+    /// Output of `zoxide query --list --score`
+    Zoxide,
+    /// autojump.txt
+    Autojump,
+    /// ~/.z
+    Z,
+    /// ~/.fasd
+    Fasd,
+    /// `cd` commands in bash or zsh history
+    History,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print the settings in use
+    Show,
+    /// Open the settings file in $VISUAL or $EDITOR and check it after editing
+    Edit,
+    /// Print the path of the settings file
+    Path,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BackupAction {
+    /// List the backups, newest first
+    List,
+    /// Show what has changed in the catalog since the backup
+    Diff {
+        #[arg(help = "Backup ID from `backup list`")]
+        id: u64,
+    },
+    /// Replace the catalog with the backup. The current catalog is backed up first
+    Restore {
+        #[arg(help = "Backup ID from `backup list`")]
+        id: u64,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+}
 
 #[derive(Debug, Subcommand)]
 pub enum Mode {
     AddPath {
         path: String,
+        #[arg(short, long, help = "Name of the path. Asked from stdin if not given")]
+        name: Option<String>,
+        #[arg(
+            short,
+            long,
+            help = "Description of the path. Asked from stdin if not given"
+        )]
+        description: Option<String>,
+        #[arg(
+            short,
+            long,
+            help = "Override the path without asking if it already exists"
+        )]
+        force: bool,
+        #[arg(
+            long,
+            conflicts_with = "force",
+            help = "Never override the path if it already exists"
+        )]
+        no_overwrite: bool,
+        #[arg(
+            short,
+            long = "tag",
+            help = "Tag the path. Can be given multiple times"
+        )]
+        tags: Vec<String>,
+    },
+    #[command(group(ArgGroup::new("target").required(true).args(["path", "name", "query"])))]
+    Remove {
+        #[arg(help = "Saved path to remove")]
+        path: Option<String>,
+        #[arg(short, long, help = "Remove paths with this name")]
+        name: Option<String>,
+        #[arg(
+            short,
+            long,
+            allow_hyphen_values = true,
+            help = "Remove all paths matching this search"
+        )]
+        query: Option<String>,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+        #[arg(long, help = "Only show what would be removed")]
+        dry_run: bool,
+    },
+    #[command(group(
+        ArgGroup::new("changes")
+            .required(true)
+            .multiple(true)
+            .args(["name", "description", "move_to", "tags", "untags"])
+    ))]
+    Edit {
+        #[arg(help = "Saved path to edit")]
+        path: String,
+        #[arg(short, long, help = "New name of the path")]
+        name: Option<String>,
+        #[arg(short, long, help = "New description of the path")]
+        description: Option<String>,
+        #[arg(short, long, help = "Change the saved path to this directory")]
+        move_to: Option<String>,
+        #[arg(short, long = "tag", help = "Add a tag. Can be given multiple times")]
+        tags: Vec<String>,
+        #[arg(
+            short,
+            long = "untag",
+            help = "Remove a tag. Can be given multiple times"
+        )]
+        untags: Vec<String>,
     },
     Pwd {
         path: Option<String>,
     },
+    List {
+        #[arg(short, long, value_enum, default_value_t = ListFormat::Table, help = "Output format")]
+        format: ListFormat,
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            help = "Only list paths matching the search"
+        )]
+        input: Vec<String>,
+    },
+    Query {
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            help = "Print the full path of the best match of this search"
+        )]
+        input: Vec<String>,
+    },
+    /// Inspect and edit the settings file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Replace the catalog with an empty one. The old catalog is backed up
+    Reset {
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+    /// Inspect and restore the automatic backups of the catalog
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    /// Print saved paths in a format that can be shared and imported
+    Export {
+        #[arg(short, long, value_enum, default_value_t = ExchangeFormat::Json, help = "Output format")]
+        format: ExchangeFormat,
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            help = "Only export paths matching the search"
+        )]
+        input: Vec<String>,
+    },
+    /// Save paths from an export or the directories another directory jumper has recorded
+    Import {
+        #[arg(
+            long,
+            value_enum,
+            conflicts_with_all = ["format", "replace"],
+            help = "Jumper to import from"
+        )]
+        from: Option<Jumper>,
+        #[arg(short, long, value_enum, default_value_t = ExchangeFormat::Json, help = "Format of the exported file")]
+        format: ExchangeFormat,
+        #[arg(
+            help = "File to import. Exports are read from stdin and jumper databases from their own location if not given"
+        )]
+        file: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Keep saved paths that conflict with imported ones (default)"
+        )]
+        merge: bool,
+        #[arg(
+            long,
+            conflicts_with = "merge",
+            help = "Replace saved paths that conflict with imported ones"
+        )]
+        replace: bool,
+        #[arg(long, help = "Only show what would be imported")]
+        dry_run: bool,
+    },
+    /// Print shell integration script. Add `eval "$(path-manager init bash)"` to your rc file
+    Init {
+        #[arg(value_enum)]
+        shell: Shell,
+        #[arg(long, default_value = "j", help = "Name of the jump function")]
+        cmd: String,
+        #[arg(long, help = "Don't bind Ctrl-G to the jump function")]
+        no_bind: bool,
+    },
     Tui {
         #[arg(short, long, help = "Launch TUI in edit mode")]
         edit: bool,
+        #[arg(
+            short,
+            long,
+            conflicts_with = "edit",
+            help = "Print the selected path to stdout instead of using tmux"
+        )]
+        print: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["edit", "print"],
+            help = "Write the selected path to this file descriptor instead of using tmux"
+        )]
+        output_fd: Option<i32>,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         input: Vec<String>,
     },
 }
@@ -20,10 +276,76 @@ pub enum Mode {
 pub struct Args {
     #[command(help = "Which mode the program is launch in", subcommand)]
     mode: Mode,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "How searches are matched. Defaults to the search.mode setting"
+    )]
+    search: Option<SearchMode>,
+    #[arg(
+        long,
+        global = true,
+        help = "Don't match search words against path descriptions"
+    )]
+    no_description_search: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "Catalog file of the saved paths. Overrides PATH_MANAGER_FILE"
+    )]
+    catalog: Option<PathBuf>,
 }
 
 impl Args {
     pub fn mode(&self) -> &Mode {
         &self.mode
     }
+
+    pub fn catalog(&self) -> Option<&Path> {
+        self.catalog.as_deref()
+    }
+
+    /// Search options from the flags, or from `settings` if the flags aren't given
+    pub fn search_options(&self, settings: &SearchSettings) -> SearchOptions {
+        SearchOptions {
+            mode: self.search.unwrap_or(settings.mode),
+            descriptions: settings.descriptions && !self.no_description_search,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, Mode};
+    use clap::Parser;
+
+    #[test]
+    fn test_negated_search_words() {
+        let args = Args::try_parse_from(["path-manager", "query", "foo", "-old"]).unwrap();
+        let Mode::Query { input } = args.mode() else {
+            panic!("Expected query mode");
+        };
+        assert_eq!(input, &["foo", "-old"]);
+
+        let args =
+            Args::try_parse_from(["path-manager", "list", "-f", "paths", "-old", "foo"]).unwrap();
+        let Mode::List { input, .. } = args.mode() else {
+            panic!("Expected list mode");
+        };
+        assert_eq!(input, &["-old", "foo"]);
+
+        let args = Args::try_parse_from(["path-manager", "query", "--", "-old"]).unwrap();
+        let Mode::Query { input } = args.mode() else {
+            panic!("Expected query mode");
+        };
+        assert_eq!(input, &["-old"]);
+
+        let args = Args::try_parse_from(["path-manager", "remove", "-q", "-old"]).unwrap();
+        let Mode::Remove { query, .. } = args.mode() else {
+            panic!("Expected remove mode");
+        };
+        assert_eq!(query.as_deref(), Some("-old"));
+    }
 }
@@ -1,47 +1,701 @@
 use std::{
-    env, fs,
-    io::{ErrorKind, Write},
-    path,
+    cmp::Reverse,
+    env,
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use crate::paths::PathItems;
+use crate::{
+    error::{Error, Result},
+    migrations::{FormatError, CATALOG_VERSION},
+    paths::PathItems,
+    settings::StorageSettings,
+};
+
+/// Environment variable pointing to the catalog file
+pub const CATALOG_ENV: &str = "PATH_MANAGER_FILE";
+/// Environment variable pointing to the settings file
+pub const SETTINGS_ENV: &str = "PATH_MANAGER_CONFIG";
+const APP_DIR: &str = "path-manager";
+const CATALOG_FILE: &str = "paths.json";
+const SETTINGS_FILE: &str = "config.toml";
+/// Directory next to the catalog that has the rolling backups
+const BACKUP_DIR: &str = "backups";
+
+/// Directory from an XDG variable, or `fallback` under HOME if it's unset.
+/// Relative paths are ignored like the XDG base directory spec says
+fn xdg_dir(var: &dyn Fn(&str) -> Option<OsString>, name: &str, fallback: &str) -> Option<PathBuf> {
+    match var(name).map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => Some(dir),
+        _ => var("HOME")
+            .filter(|home| !home.is_empty())
+            .map(|home| PathBuf::from(home).join(fallback)),
+    }
+}
+
+/// Find the catalog file. The first one of these is used:
+///  - `file` given from the command line
+///  - `PATH_MANAGER_FILE` environment variable
+///  - `setting` from the storage.catalog setting
+///  - `$XDG_DATA_HOME/path-manager/paths.json`, unless the catalog only exists
+///    in the old `$XDG_CONFIG_HOME/path-manager/paths.json` location
+fn catalog_file(
+    file: Option<&Path>,
+    setting: Option<&Path>,
+    var: &dyn Fn(&str) -> Option<OsString>,
+) -> Option<PathBuf> {
+    if let Some(file) = file {
+        return Some(file.into());
+    }
+
+    if let Some(file) = var(CATALOG_ENV).filter(|file| !file.is_empty()) {
+        return Some(file.into());
+    }
+
+    if let Some(file) = setting {
+        return Some(file.into());
+    }
+
+    let data_file = xdg_dir(var, "XDG_DATA_HOME", ".local/share")
+        .map(|dir| dir.join(APP_DIR).join(CATALOG_FILE));
+    let legacy_file =
+        xdg_dir(var, "XDG_CONFIG_HOME", ".config").map(|dir| dir.join(APP_DIR).join(CATALOG_FILE));
+
+    match (data_file, legacy_file) {
+        (Some(data_file), Some(legacy_file)) if !data_file.exists() && legacy_file.exists() => {
+            Some(legacy_file)
+        }
+        (data_file, _) => data_file,
+    }
+}
+
+/// Settings file from `PATH_MANAGER_CONFIG` or `$XDG_CONFIG_HOME/path-manager/config.toml`
+fn settings_file(var: &dyn Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    if let Some(file) = var(SETTINGS_ENV).filter(|file| !file.is_empty()) {
+        return Some(file.into());
+    }
+
+    xdg_dir(var, "XDG_CONFIG_HOME", ".config").map(|dir| dir.join(APP_DIR).join(SETTINGS_FILE))
+}
+
+/// Find the settings file from the environment or the XDG base directories
+pub fn locate_settings() -> Result<PathBuf> {
+    settings_file(&|name| env::var_os(name)).ok_or_else(|| {
+        Error::Config(format!(
+            "Cannot find the settings file. Set HOME, XDG_CONFIG_HOME or {SETTINGS_ENV}"
+        ))
+    })
+}
 
-fn paths_file() -> String {
-    let home = env::var("HOME").expect("HOME not found in env");
-    let full_dir_path = format!("{home}/.config/path-manager");
-    let dir_path = path::Path::new(&full_dir_path);
-    if !dir_path.exists() {
-        fs::create_dir_all(dir_path).unwrap_or_else(|_| panic!("Cannot create {full_dir_path}"))
+/// Catalog file exists but can't be parsed. A copy of the file is saved to `backup`
+#[derive(Debug)]
+pub struct CorruptCatalog {
+    pub file: PathBuf,
+    pub backup: Option<PathBuf>,
+    pub error: serde_json::Error,
+}
+
+impl fmt::Display for CorruptCatalog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Catalog {} is corrupt: {}",
+            self.file.display(),
+            self.error
+        )?;
+        if let Some(backup) = &self.backup {
+            writeln!(f, "A copy of it is saved in {}", backup.display())?;
+        }
+        write!(
+            f,
+            "Fix the file or run `path-manager reset` to start from an empty catalog"
+        )
     }
+}
+
+/// `file` with `extension` appended to the file name
+fn sibling(file: &Path, extension: &str) -> PathBuf {
+    let mut file = file.to_path_buf().into_os_string();
+    file.push(extension);
+    file.into()
+}
+
+/// `dir/<prefix><unix millis>` that doesn't exist yet. Milliseconds are counted up
+/// past existing files so copies made in the same millisecond don't overwrite each other
+fn unused_file(dir: &Path, prefix: &str) -> PathBuf {
+    let mut time = unix_millis();
+    while dir.join(format!("{prefix}{time}")).exists() {
+        time += 1;
+    }
+    dir.join(format!("{prefix}{time}"))
+}
+
+/// Automatic copy of the catalog from before it was changed
+#[derive(Debug)]
+pub struct Backup {
+    /// Unix time in milliseconds when the backup was made
+    pub id: u64,
+    pub file: PathBuf,
+}
+
+/// Milliseconds since the unix epoch, used as backup ids
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis() as u64)
+}
 
-    format!("{full_dir_path}/paths.json")
+/// The file the saved paths are stored in
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    file: PathBuf,
+    /// How many rolling backups are kept
+    backups: usize,
 }
 
-fn read_paths_file() -> String {
-    let path = paths_file();
+impl Catalog {
+    /// Find the catalog file from `file`, the environment, the storage settings
+    /// or the XDG base directories
+    pub fn locate(file: Option<&Path>, storage: &StorageSettings) -> Result<Self> {
+        catalog_file(file, storage.catalog.as_deref(), &|name| env::var_os(name))
+            .map(|file| Self {
+                file,
+                backups: storage.backups,
+            })
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "Cannot find the path catalog. Set HOME, XDG_DATA_HOME or {CATALOG_ENV}, or use --catalog"
+                ))
+            })
+    }
+
+    fn read(&self) -> Result<String> {
+        match fs::read_to_string(&self.file) {
+            Ok(text) => Ok(text),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok("".into()),
+            Err(e) => Err(Error::io(format!("Cannot read {}", self.file.display()), e)),
+        }
+    }
+
+    /// Parse the catalog `file_data`. A corrupt catalog is backed up if `back_up` is true.
+    /// Returns the version the catalog was in
+    fn parse(&self, file_data: &str, back_up: bool) -> Result<(PathItems, u64)> {
+        match PathItems::from_json(file_data) {
+            Ok(parsed) => Ok(parsed),
+            Err(FormatError::Syntax(error)) => Err(Error::from(CorruptCatalog {
+                file: self.file.clone(),
+                backup: back_up
+                    .then(|| self.backup("corrupt", file_data).ok())
+                    .flatten(),
+                error,
+            })),
+            Err(FormatError::NewerVersion(version)) => Err(Error::Version(format!(
+                "Catalog {} is version {version} but this path-manager only supports \
+                 versions up to {CATALOG_VERSION}. Update path-manager to use it",
+                self.file.display()
+            ))),
+        }
+    }
+
+    /// Read and parse the catalog. Returns true if the catalog was in an older format.
+    /// A corrupt catalog is backed up and never overwritten.
+    /// An older catalog is backed up before it's upgraded
+    fn read_items(&self) -> Result<(PathItems, bool)> {
+        let file_data = self.read()?;
+        let (items, version) = self.parse(&file_data, true)?;
+
+        let migrated = version != CATALOG_VERSION;
+        if migrated {
+            self.backup(&format!("v{version}"), &file_data)
+                .map_err(|e| Error::io(format!("Cannot back up {}", self.file.display()), e))?;
+        }
+        Ok((items, migrated))
+    }
+
+    /// Load saved file paths.
+    /// PathItems will be empty if no files are saved.
+    /// Catalogs in an older format are upgraded right away
+    pub fn load(&self) -> Result<PathItems> {
+        let (items, migrated) = self.read_items()?;
+        if migrated {
+            self.update(|_| Ok(true))?;
+        }
+        Ok(items)
+    }
+
+    /// Load saved file paths without writing anything, for previews.
+    /// Catalogs in an older format are only upgraded in memory
+    pub fn load_read_only(&self) -> Result<PathItems> {
+        let file_data = self.read()?;
+        self.parse(&file_data, false).map(|(items, _)| items)
+    }
+
+    /// Save a copy of the catalog `data` as `paths.json.<kind>-<unix millis>`.
+    /// Nothing is saved if an identical copy of the same kind already exists
+    fn backup(&self, kind: &str, data: &str) -> io::Result<PathBuf> {
+        let dir = self.file.parent().unwrap_or(Path::new("."));
+        let file_name = self.file.file_name().unwrap_or_default().to_string_lossy();
+        let prefix = format!("{file_name}.{kind}-");
+        let existing = fs::read_dir(dir)?.flatten().find(|entry| {
+            entry.file_name().to_string_lossy().starts_with(&prefix)
+                && fs::read_to_string(entry.path()).is_ok_and(|old| old == data)
+        });
+        if let Some(existing) = existing {
+            return Ok(existing.path());
+        }
+
+        let backup = unused_file(dir, &prefix);
+        fs::write(&backup, data)?;
+        Ok(backup)
+    }
+
+    fn backup_dir(&self) -> PathBuf {
+        self.file
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(BACKUP_DIR)
+    }
+
+    /// Rolling backups of the catalog, newest first
+    pub fn backups(&self) -> Result<Vec<Backup>> {
+        let dir = self.backup_dir();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io(format!("Cannot read {}", dir.display()), e)),
+        };
+
+        // Backups of paths.json are named like paths.json.1792301879123
+        let file_name = self.file.file_name().unwrap_or_default().to_string_lossy();
+        let prefix = format!("{file_name}.");
+        let mut backups: Vec<Backup> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let id = name.to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+                Some(Backup {
+                    id,
+                    file: entry.path(),
+                })
+            })
+            .collect();
+        backups.sort_by_key(|backup| Reverse(backup.id));
+        Ok(backups)
+    }
 
-    match fs::read_to_string(path) {
-        Ok(text) => text,
-        Err(e) => {
-            if e.kind() != ErrorKind::NotFound {
-                panic!("{:?}", e.kind())
-            } else {
-                "".into()
+    /// Copy the catalog into the backup directory and remove the oldest backups
+    /// so only the configured amount is kept
+    fn rotate_backups(&self) -> Result<()> {
+        if self.backups == 0 || !self.file.exists() {
+            return Ok(());
+        }
+
+        let dir = self.backup_dir();
+        let file_name = self.file.file_name().unwrap_or_default().to_string_lossy();
+        let backup = unused_file(&dir, &format!("{file_name}."));
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::copy(&self.file, &backup))
+            .map_err(|e| Error::io(format!("Cannot write {}", backup.display()), e))?;
+
+        for old in self.backups()?.into_iter().skip(self.backups) {
+            fs::remove_file(&old.file)
+                .map_err(|e| Error::io(format!("Cannot remove {}", old.file.display()), e))?;
+        }
+        Ok(())
+    }
+
+    /// Load the paths saved in backup `id`
+    pub fn load_backup(&self, id: u64) -> Result<PathItems> {
+        let Some(backup) = self.backups()?.into_iter().find(|backup| backup.id == id) else {
+            return Err(Error::NotFound(format!("Backup {id} doesn't exist")));
+        };
+
+        let data = fs::read_to_string(&backup.file)
+            .map_err(|e| Error::io(format!("Cannot read {}", backup.file.display()), e))?;
+        match PathItems::from_json(&data) {
+            Ok((items, _)) => Ok(items),
+            Err(FormatError::Syntax(e)) => Err(Error::Validation(format!(
+                "Backup {} is corrupt: {e}",
+                backup.file.display()
+            ))),
+            Err(FormatError::NewerVersion(version)) => Err(Error::Version(format!(
+                "Backup {} is version {version} but this path-manager only supports \
+                 versions up to {CATALOG_VERSION}",
+                backup.file.display()
+            ))),
+        }
+    }
+
+    /// Replace the catalog with backup `id`. The current catalog is backed up first
+    pub fn restore(&self, id: u64) -> Result<()> {
+        let restored = self.load_backup(id)?;
+        self.update(|items| {
+            *items = restored;
+            Ok(true)
+        })
+    }
+
+    /// `file` with `extension` appended to the file name, like `paths.json.lock`
+    fn sibling(&self, extension: &str) -> PathBuf {
+        sibling(&self.file, extension)
+    }
+
+    fn create_dir(&self) -> Result<()> {
+        match self.file.parent() {
+            Some(dir) => fs::create_dir_all(dir)
+                .map_err(|e| Error::io(format!("Cannot create {}", dir.display()), e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Take an exclusive lock of the catalog. Blocks until other invocations release it
+    fn lock(&self) -> Result<CatalogLock> {
+        self.create_dir()?;
+        let lock_path = self.sibling(".lock");
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .and_then(|file| file.lock().map(|_| file))
+            .map_err(|e| Error::io(format!("Cannot lock {}", lock_path.display()), e))?;
+        Ok(CatalogLock { _file: file })
+    }
+
+    /// Replace the catalog with `items`. The items are written to a temporary file
+    /// first so the catalog is never left half written
+    fn save(&self, items: PathItems) -> Result<()> {
+        // A symlinked catalog, like one in a dotfiles repository, is written through
+        // so the link stays a link
+        let file = fs::canonicalize(&self.file).unwrap_or_else(|_| self.file.clone());
+        let permissions = fs::metadata(&file).map(|m| m.permissions()).ok();
+
+        let tmp_path = sibling(&file, ".tmp");
+        let json_str = items.into_json();
+        File::create(&tmp_path)
+            .and_then(|mut output| {
+                if let Some(permissions) = permissions {
+                    output.set_permissions(permissions)?;
+                }
+                write!(output, "{json_str}")?;
+                output.sync_all()
+            })
+            .map_err(|e| Error::io(format!("Cannot write {}", tmp_path.display()), e))?;
+        fs::rename(&tmp_path, &file)
+            .map_err(|e| Error::io(format!("Cannot replace {}", file.display()), e))?;
+
+        // Make the rename itself durable. Not every platform can sync directories
+        if let Some(dir) = file.parent() {
+            let _ = File::open(dir).and_then(|dir| dir.sync_all());
+        }
+        Ok(())
+    }
+
+    /// Load the catalog, let `change` modify it and save it if `change` returns true.
+    /// The catalog is locked the whole time so concurrent invocations
+    /// don't overwrite each other's changes.
+    /// The old catalog is backed up unless only the visits changed
+    pub fn update(&self, change: impl FnOnce(&mut PathItems) -> Result<bool>) -> Result<()> {
+        let _lock = self.lock()?;
+        let (mut items, migrated) = self.read_items()?;
+        let before = items.clone();
+        if change(&mut items)? || migrated {
+            if !before.diff(&items).is_empty() {
+                self.rotate_backups()?;
             }
+            self.save(items)?;
         }
+        Ok(())
+    }
+
+    /// Replace the catalog with an empty one, even if it's corrupt.
+    /// Returns where the old catalog was backed up to
+    pub fn reset(&self) -> Result<Option<PathBuf>> {
+        let _lock = self.lock()?;
+        let backup = match fs::read_to_string(&self.file) {
+            Ok(data) => Some(
+                self.backup("reset", &data)
+                    .map_err(|e| Error::io(format!("Cannot back up {}", self.file.display()), e))?,
+            ),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(Error::io(format!("Cannot read {}", self.file.display()), e)),
+        };
+        self.save(PathItems::default())?;
+        Ok(backup)
     }
 }
 
-/// Load saved file paths.
-/// PathItems will be empty if no files are saved.
-pub fn load_saved_paths() -> PathItems {
-    let file_data = read_paths_file();
-    PathItems::from_json(&file_data)
+/// Exclusive lock of the catalog, released when dropped
+struct CatalogLock {
+    _file: File,
 }
 
-pub fn save_paths(items: PathItems) {
-    let path = paths_file();
-    let json_str = items.into_json();
-    let mut output = fs::File::create(path).expect("Cannot paths.json file");
-    write!(output, "{json_str}").unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::PathItem;
+    use std::ops::Deref;
+
+    fn env_of<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.into())
+        }
+    }
+
+    #[test]
+    fn test_catalog_file() {
+        let home = env::temp_dir().join(format!("path-manager-test-{}", std::process::id()));
+        let home_str = home.to_str().unwrap();
+        let vars = [("HOME", home_str)];
+        let var = env_of(&vars);
+
+        assert_eq!(
+            catalog_file(None, None, &var),
+            Some(home.join(".local/share/path-manager/paths.json"))
+        );
+        assert_eq!(
+            catalog_file(Some(Path::new("/tmp/flag.json")), None, &var),
+            Some("/tmp/flag.json".into())
+        );
+
+        let vars = [
+            ("HOME", home_str),
+            ("XDG_DATA_HOME", "/xdg/data"),
+            (CATALOG_ENV, "/tmp/env.json"),
+        ];
+        let var = env_of(&vars);
+        assert_eq!(catalog_file(None, None, &var), Some("/tmp/env.json".into()));
+        let setting = Some(Path::new("/tmp/setting.json"));
+        assert_eq!(
+            catalog_file(None, setting, &var),
+            Some("/tmp/env.json".into())
+        );
+        assert_eq!(
+            catalog_file(None, setting, &env_of(&[("HOME", home_str)])),
+            Some("/tmp/setting.json".into())
+        );
+        assert_eq!(
+            catalog_file(Some(Path::new("/tmp/flag.json")), None, &var),
+            Some("/tmp/flag.json".into())
+        );
+
+        let vars = [
+            ("HOME", home_str),
+            ("XDG_DATA_HOME", "/xdg/data"),
+            ("XDG_CONFIG_HOME", "relative"),
+        ];
+        let var = env_of(&vars);
+        assert_eq!(
+            catalog_file(None, None, &var),
+            Some("/xdg/data/path-manager/paths.json".into())
+        );
+
+        // The old catalog is used until it's moved to the data directory
+        let legacy_dir = home.join(".config/path-manager");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join("paths.json"), "").unwrap();
+        let vars = [("HOME", home_str)];
+        let var = env_of(&vars);
+        assert_eq!(
+            catalog_file(None, None, &var),
+            Some(legacy_dir.join("paths.json"))
+        );
+        fs::remove_dir_all(&home).unwrap();
+
+        assert_eq!(catalog_file(None, None, &env_of(&[])), None);
+    }
+
+    #[test]
+    fn test_settings_file() {
+        let vars = [("HOME", "/home/user")];
+        assert_eq!(
+            settings_file(&env_of(&vars)),
+            Some("/home/user/.config/path-manager/config.toml".into())
+        );
+
+        let vars = [("HOME", "/home/user"), ("XDG_CONFIG_HOME", "/xdg/config")];
+        assert_eq!(
+            settings_file(&env_of(&vars)),
+            Some("/xdg/config/path-manager/config.toml".into())
+        );
+
+        let vars = [
+            ("XDG_CONFIG_HOME", "/xdg/config"),
+            (SETTINGS_ENV, "/tmp/s.toml"),
+        ];
+        assert_eq!(settings_file(&env_of(&vars)), Some("/tmp/s.toml".into()));
+        assert_eq!(settings_file(&env_of(&[])), None);
+    }
+
+    /// Catalog in its own temporary directory. The directory is removed when dropped,
+    /// also when the test fails
+    struct TempCatalog {
+        dir: PathBuf,
+        catalog: Catalog,
+    }
+
+    impl Deref for TempCatalog {
+        type Target = Catalog;
+
+        fn deref(&self) -> &Catalog {
+            &self.catalog
+        }
+    }
+
+    impl Drop for TempCatalog {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn temp_catalog(name: &str, backups: usize) -> TempCatalog {
+        let dir = env::temp_dir().join(format!("path-manager-{name}-{}", std::process::id()));
+        // Files left by an earlier run would change the results
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempCatalog {
+            catalog: Catalog {
+                file: dir.join("paths.json"),
+                backups,
+            },
+            dir,
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        let catalog = temp_catalog("update", 0);
+
+        catalog
+            .update(|items| {
+                items.add_path(PathItem::new("a".into(), "/a".into(), "".into()));
+                Ok(true)
+            })
+            .unwrap();
+        catalog
+            .update(|items| {
+                items.add_path(PathItem::new("b".into(), "/b".into(), "".into()));
+                Ok(false)
+            })
+            .unwrap();
+        let items = catalog.load().unwrap();
+        assert_eq!(items.paths.len(), 1);
+        assert!(items.exists("/a"));
+        assert!(!catalog.sibling(".tmp").exists());
+    }
+
+    #[test]
+    fn test_corrupt_catalog() {
+        let catalog = temp_catalog("corrupt", 0);
+        let broken = "{\"paths\": [";
+        fs::write(&catalog.file, broken).unwrap();
+
+        let corrupt_backup = || match catalog.load() {
+            Err(Error::Corrupt(corrupt)) => corrupt.backup.unwrap(),
+            other => panic!("Catalog isn't corrupt: {other:?}"),
+        };
+        let backup = corrupt_backup();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), broken);
+        // Loading again doesn't make another copy
+        assert_eq!(corrupt_backup(), backup);
+        assert_eq!(fs::read_dir(&catalog.dir).unwrap().count(), 2);
+        // Different corrupt versions saved right after each other are all kept
+        let other = catalog.backup("corrupt", "{").unwrap();
+        assert_ne!(other, backup);
+        assert_eq!(fs::read_to_string(&backup).unwrap(), broken);
+        fs::remove_file(other).unwrap();
+
+        // Corrupt catalog is never written over
+        assert!(catalog.update(|_| Ok(true)).is_err());
+        assert_eq!(fs::read_to_string(&catalog.file).unwrap(), broken);
+
+        let backup = catalog.reset().unwrap().unwrap();
+        assert_eq!(fs::read_to_string(backup).unwrap(), broken);
+        assert!(catalog.load().unwrap().paths.is_empty());
+    }
+
+    #[test]
+    fn test_backups() {
+        let catalog = temp_catalog("backups", 2);
+        let add = |path: &str| {
+            catalog
+                .update(|items| {
+                    items.add_path(PathItem::new(path.into(), path.into(), "".into()));
+                    Ok(true)
+                })
+                .unwrap()
+        };
+
+        // Nothing to back up before the catalog exists
+        add("/a");
+        assert!(catalog.backups().unwrap().is_empty());
+        add("/b");
+        add("/c");
+        // Only visits changed
+        catalog.update(|items| Ok(items.visit("/a"))).unwrap();
+        add("/d");
+
+        let backups = catalog.backups().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups[0].id > backups[1].id);
+        assert_eq!(catalog.load_backup(backups[0].id).unwrap().paths.len(), 3);
+        assert_eq!(catalog.load_backup(backups[1].id).unwrap().paths.len(), 2);
+
+        catalog.restore(backups[1].id).unwrap();
+        assert_eq!(catalog.load().unwrap().paths.len(), 2);
+        // Restoring backed up the catalog from before the restore
+        let newest = &catalog.backups().unwrap()[0];
+        assert_eq!(catalog.load_backup(newest.id).unwrap().paths.len(), 4);
+
+        assert!(matches!(catalog.load_backup(1), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn test_symlinked_catalog() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let catalog = temp_catalog("symlink", 0);
+        let real = catalog.dir.join("dotfiles/paths.json");
+        fs::create_dir_all(real.parent().unwrap()).unwrap();
+        fs::write(&real, "").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o600)).unwrap();
+        let link = &catalog.file;
+        symlink(&real, link).unwrap();
+
+        catalog
+            .update(|items| {
+                items.add_path(PathItem::new("a".into(), "/a".into(), "".into()));
+                Ok(true)
+            })
+            .unwrap();
+
+        assert!(fs::symlink_metadata(link).unwrap().is_symlink());
+        assert!(catalog.load().unwrap().exists("/a"));
+        let mode = fs::metadata(&real).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_load_read_only() {
+        let catalog = temp_catalog("read-only", 10);
+        let old = r#"{"paths": [{"name": "a", "full_path": "/a", "description": ""}]}"#;
+        fs::write(&catalog.file, old).unwrap();
+
+        // Older catalog is upgraded only in memory
+        assert!(catalog.load_read_only().unwrap().exists("/a"));
+        assert_eq!(fs::read_to_string(&catalog.file).unwrap(), old);
+
+        fs::write(&catalog.file, "{").unwrap();
+        assert!(matches!(
+            catalog.load_read_only(),
+            Err(Error::Corrupt(CorruptCatalog { backup: None, .. }))
+        ));
+        assert_eq!(fs::read_dir(&catalog.dir).unwrap().count(), 1);
+    }
 }
@@ -0,0 +1,87 @@
+//! Errors of the whole program and the exit codes they are reported with
+
+use std::{fmt, io};
+
+use crate::config_path::CorruptCatalog;
+
+/// Everything that can make a mode fail
+#[derive(Debug)]
+pub enum Error {
+    /// User didn't confirm the action. The reason is printed if there is one
+    Aborted(Option<String>),
+    /// Given value can't be used, like a path that isn't valid UTF-8
+    Validation(String),
+    /// Path doesn't exist in the filesystem or nothing matched the search
+    NotFound(String),
+    /// Path is already saved and wasn't overridden
+    AlreadyExists(String),
+    /// Catalog file can't be found or the settings are invalid
+    Config(String),
+    /// Catalog file can't be parsed
+    Corrupt(CorruptCatalog),
+    /// Catalog file was written by a newer version of the program
+    Version(String),
+    /// Reading or writing a file or the terminal failed
+    Io { context: String, source: io::Error },
+    /// tmux isn't running or a tmux command failed
+    Tmux(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Exit code the program exits with. 2 is also used by clap for invalid arguments
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Aborted(_) => 1,
+            Self::Validation(_) => 2,
+            Self::NotFound(_) => 3,
+            Self::AlreadyExists(_) => 4,
+            Self::Config(_) => 5,
+            Self::Corrupt(_) => 6,
+            Self::Io { .. } => 7,
+            Self::Tmux(_) => 8,
+            Self::Version(_) => 9,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Aborted(None) => Ok(()),
+            Self::Aborted(Some(message))
+            | Self::Validation(message)
+            | Self::NotFound(message)
+            | Self::AlreadyExists(message)
+            | Self::Config(message)
+            | Self::Tmux(message)
+            | Self::Version(message) => write!(f, "{message}"),
+            Self::Corrupt(corrupt) => write!(f, "{corrupt}"),
+            Self::Io { context, source } => write!(f, "{context}: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Corrupt(corrupt) => Some(&corrupt.error),
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<CorruptCatalog> for Error {
+    fn from(corrupt: CorruptCatalog) -> Self {
+        Self::Corrupt(corrupt)
+    }
+}
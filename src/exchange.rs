@@ -0,0 +1,396 @@
+/// exchange.rs converts saved paths to and from formats for sharing them with other
+/// people and machines. Only the details users write are exchanged, visits stay local
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::ExchangeFormat,
+    error::{Error, Result},
+    migrations::{FormatError, CATALOG_VERSION},
+    paths::{PathItem, PathItems},
+};
+
+/// Path entry in the JSON and TOML formats
+#[derive(Debug, Serialize, Deserialize)]
+struct ExchangeItem {
+    name: String,
+    full_path: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// TOML documents have to be tables so the paths are in a `[[paths]]` array
+#[derive(Debug, Serialize, Deserialize)]
+struct ExchangeFile {
+    paths: Vec<ExchangeItem>,
+}
+
+impl From<&PathItem> for ExchangeItem {
+    fn from(item: &PathItem) -> Self {
+        Self {
+            name: item.name.clone(),
+            full_path: item.full_path.clone(),
+            description: item.description.clone(),
+            tags: item.tags.clone(),
+        }
+    }
+}
+
+/// Columns of the CSV and Markdown tables
+const COLUMNS: [&str; 4] = ["name", "full_path", "description", "tags"];
+/// Markdown tables have headers for humans
+const MARKDOWN_HEADER: [&str; 4] = ["Name", "Path", "Description", "Tags"];
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// Format `items` in given `format`. Every format ends with a newline
+pub fn export(items: &[&PathItem], format: ExchangeFormat) -> String {
+    let exchange = || items.iter().map(|&item| item.into()).collect();
+    match format {
+        ExchangeFormat::Json => {
+            let items: Vec<ExchangeItem> = exchange();
+            serde_json::to_string_pretty(&items).unwrap() + "\n"
+        }
+        // Paths only contain strings so serializing can't fail
+        ExchangeFormat::Toml => {
+            toml::to_string_pretty(&ExchangeFile { paths: exchange() }).unwrap()
+        }
+        ExchangeFormat::Csv => {
+            let mut lines = vec![COLUMNS.join(",")];
+            for item in items {
+                let fields = [
+                    &item.name,
+                    &item.full_path,
+                    &item.description,
+                    &item.tags.join(","),
+                ];
+                let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                lines.push(fields.join(","));
+            }
+            lines.into_iter().map(|line| line + "\n").collect()
+        }
+        ExchangeFormat::Markdown => {
+            let mut lines = vec![
+                format!("| {} |", MARKDOWN_HEADER.join(" | ")),
+                "| --- | --- | --- | --- |".to_string(),
+            ];
+            for item in items {
+                lines.push(format!(
+                    "| {} | `{}` | {} | {} |",
+                    markdown_cell(&item.name),
+                    markdown_cell(&item.full_path),
+                    markdown_cell(&item.description),
+                    markdown_cell(&item.tags.join(", "))
+                ));
+            }
+            lines.into_iter().map(|line| line + "\n").collect()
+        }
+    }
+}
+
+/// Split CSV into records of fields. Returns the records with the line they start on
+fn parse_csv(text: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            (c, _) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if in_quotes {
+        return Err(Error::Validation(format!(
+            "Invalid CSV on line {record_line}: quoted field isn't closed"
+        )));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    // Empty lines don't have paths
+    records.retain(|(_, record)| record.iter().any(|f| !f.is_empty()));
+    Ok(records)
+}
+
+/// Split the rows of a Markdown table into cells. Separator rows and lines outside
+/// the table are skipped
+fn parse_markdown(text: &str) -> Vec<(usize, Vec<String>)> {
+    let mut rows = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let Some(row) = line.trim().strip_prefix('|') else {
+            continue;
+        };
+        let row = row.strip_suffix('|').unwrap_or(row);
+        if row.chars().all(|c| matches!(c, '-' | ':' | '|' | ' ')) {
+            continue;
+        }
+
+        let mut cells = vec![String::new()];
+        let mut chars = row.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&'|') => {
+                    chars.next();
+                    cells.last_mut().unwrap().push('|');
+                }
+                '|' => cells.push(String::new()),
+                c => cells.last_mut().unwrap().push(c),
+            }
+        }
+        let cells = cells.iter().map(|cell| cell.trim().to_string()).collect();
+        rows.push((i + 1, cells));
+    }
+    rows
+}
+
+/// Turn table rows into paths. The first row is the header that tells which
+/// column has which field
+fn items_from_rows(format: &str, rows: Vec<(usize, Vec<String>)>) -> Result<Vec<PathItem>> {
+    let invalid = |line: usize, message: &str| {
+        Error::Validation(format!("Invalid {format} on line {line}: {message}"))
+    };
+
+    let mut rows = rows.into_iter();
+    let Some((header_line, header)) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.contains(&h.to_lowercase().as_str()))
+    };
+    let name = column(&["name"]);
+    let Some(path) = column(&["full_path", "path"]) else {
+        return Err(invalid(header_line, "header doesn't have a path column"));
+    };
+    let description = column(&["description"]);
+    let tags = column(&["tags"]);
+
+    let mut items = Vec::new();
+    for (line, row) in rows {
+        let cell = |column: Option<usize>| column.and_then(|c| row.get(c)).map_or("", |c| c);
+        let full_path = cell(Some(path));
+        let full_path = full_path
+            .strip_prefix('`')
+            .and_then(|p| p.strip_suffix('`'))
+            .unwrap_or(full_path);
+        if full_path.is_empty() {
+            return Err(invalid(line, "path is empty"));
+        }
+        if !full_path.starts_with('/') {
+            return Err(invalid(line, &format!("path '{full_path}' isn't absolute")));
+        }
+
+        let mut item = PathItem::new(
+            cell(name).into(),
+            full_path.into(),
+            cell(description).into(),
+        );
+        for tag in cell(tags).split(',') {
+            item.add_tag(tag);
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Parse paths exported in `format`. JSON also accepts a whole catalog file
+pub fn import(text: &str, format: ExchangeFormat) -> Result<Vec<PathItem>> {
+    let from_exchange = |items: Vec<ExchangeItem>| {
+        items
+            .into_iter()
+            .map(|e| {
+                let mut item = PathItem::new(e.name, e.full_path, e.description);
+                for tag in &e.tags {
+                    item.add_tag(tag);
+                }
+                item
+            })
+            .collect()
+    };
+
+    let items: Vec<PathItem> = match format {
+        ExchangeFormat::Json if text.trim_start().starts_with('{') => {
+            match PathItems::from_json(text) {
+                // Visits and unknown fields of the other catalog aren't imported
+                Ok((items, _)) => from_exchange(items.paths.iter().map(Into::into).collect()),
+                Err(FormatError::Syntax(e)) => {
+                    return Err(Error::Validation(format!("Invalid JSON: {e}")))
+                }
+                Err(FormatError::NewerVersion(version)) => {
+                    return Err(Error::Version(format!(
+                        "Catalog is version {version} but this path-manager only supports \
+                         versions up to {CATALOG_VERSION}"
+                    )))
+                }
+            }
+        }
+        ExchangeFormat::Json => serde_json::from_str(text)
+            .map(from_exchange)
+            .map_err(|e| Error::Validation(format!("Invalid JSON: {e}")))?,
+        ExchangeFormat::Toml => toml::from_str(text)
+            .map(|file: ExchangeFile| from_exchange(file.paths))
+            .map_err(|e| {
+                Error::Validation(format!("Invalid TOML: {}", e.to_string().trim_end()))
+            })?,
+        ExchangeFormat::Csv => items_from_rows("CSV", parse_csv(text)?)?,
+        ExchangeFormat::Markdown => items_from_rows("Markdown", parse_markdown(text))?,
+    };
+
+    if items.iter().any(|item| item.full_path.is_empty()) {
+        return Err(Error::Validation("Imported paths can't be empty".into()));
+    }
+    // Saved paths are canonical, so a relative path would never match anything
+    if let Some(item) = items.iter().find(|item| !item.full_path.starts_with('/')) {
+        return Err(Error::Validation(format!(
+            "Imported path '{}' isn't absolute",
+            item.full_path
+        )));
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<PathItem> {
+        let mut home = PathItem::new("Home".into(), "/home/user".into(), "User's home".into());
+        home.add_tag("me");
+        home.add_tag("dotfiles");
+        home.visits = 10;
+        vec![
+            home,
+            PathItem::new(
+                "Odd | name".into(),
+                "/tmp/a,b".into(),
+                "Quote \" and\nnewline".into(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let items = items();
+        let refs: Vec<&PathItem> = items.iter().collect();
+        for format in [
+            ExchangeFormat::Json,
+            ExchangeFormat::Toml,
+            ExchangeFormat::Csv,
+            ExchangeFormat::Markdown,
+        ] {
+            let imported = import(&export(&refs, format), format).unwrap();
+            assert_eq!(imported.len(), 2, "{format:?}");
+            assert_eq!(imported[0].name, "Home", "{format:?}");
+            assert_eq!(imported[0].full_path, "/home/user", "{format:?}");
+            assert_eq!(imported[0].tags, ["me", "dotfiles"], "{format:?}");
+            // Visits aren't exported
+            assert_eq!(imported[0].visits, 0, "{format:?}");
+            assert_eq!(imported[1].name, "Odd | name", "{format:?}");
+            assert_eq!(imported[1].full_path, "/tmp/a,b", "{format:?}");
+            assert!(imported[1].same_details(&items[1]) || format == ExchangeFormat::Markdown);
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let items = items();
+        let refs: Vec<&PathItem> = items.iter().collect();
+        assert_eq!(
+            export(&refs, ExchangeFormat::Csv),
+            "name,full_path,description,tags\n\
+             Home,/home/user,User's home,\"me,dotfiles\"\n\
+             Odd | name,\"/tmp/a,b\",\"Quote \"\" and\nnewline\",\n"
+        );
+        assert_eq!(
+            export(&refs, ExchangeFormat::Markdown),
+            "| Name | Path | Description | Tags |\n\
+             | --- | --- | --- | --- |\n\
+             | Home | `/home/user` | User's home | me, dotfiles |\n\
+             | Odd \\| name | `/tmp/a,b` | Quote \" and newline |  |\n"
+        );
+    }
+
+    #[test]
+    fn test_import() {
+        // Columns are found by the header and missing columns are empty
+        let csv = "path,name\r\n/src,Source\r\n\r\n";
+        let imported = import(csv, ExchangeFormat::Csv).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].full_path, "/src");
+        assert_eq!(imported[0].description, "");
+
+        let markdown = "Our paths:\n\n| Path | Tags |\n|:--|--|\n| /src | #Work |\n";
+        let imported = import(markdown, ExchangeFormat::Markdown).unwrap();
+        assert_eq!(imported[0].full_path, "/src");
+        assert_eq!(imported[0].tags, ["work"]);
+
+        let catalog = r#"{"version": 2, "paths": [{"name": "a", "full_path": "/a",
+            "description": "", "visits": 9, "last_visited": 1700000000, "color": "red"}]}"#;
+        let imported = import(catalog, ExchangeFormat::Json).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!((imported[0].visits, imported[0].last_visited), (0, 0));
+        let mut items = PathItems::default();
+        items.paths = imported;
+        assert!(!items.into_json().contains("color"));
+
+        for (text, format) in [
+            ("name\nA\n", ExchangeFormat::Csv),
+            ("path\n\"/a\n", ExchangeFormat::Csv),
+            ("path,name\n,A\n", ExchangeFormat::Csv),
+            ("path,name\nsrc,Source\n", ExchangeFormat::Csv),
+            ("| Path |\n| --- |\n| `src` |\n", ExchangeFormat::Markdown),
+            (
+                "[{\"name\": \"a\", \"full_path\": \"src\"}]",
+                ExchangeFormat::Json,
+            ),
+            (
+                "[[paths]]\nname = \"a\"\nfull_path = \"~/src\"",
+                ExchangeFormat::Toml,
+            ),
+            ("[{\"name\": \"a\"}]", ExchangeFormat::Json),
+            ("paths = 1", ExchangeFormat::Toml),
+        ] {
+            assert!(
+                matches!(import(text, format), Err(Error::Validation(_))),
+                "{text}"
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! fuzzy.rs contains the fuzzy matcher used for searching PathItems
+
+/// Score of every matched character
+const SCORE_MATCH: i64 = 16;
+/// Penalty of starting a gap between matched characters
+const PENALTY_GAP_START: i64 = 3;
+/// Penalty of every skipped character after the first one in a gap
+const PENALTY_GAP_EXTEND: i64 = 1;
+/// Match right after the previous matched character
+const BONUS_CONSECUTIVE: i64 = 8;
+/// Match at the start of a path segment, like `m` in `/home/user/manager`
+const BONUS_SEGMENT: i64 = 10;
+/// Match at the start of a word, like `m` in `path-manager`
+const BONUS_BOUNDARY: i64 = 8;
+/// Match in the last segment of a path, since that's what people remember
+const BONUS_BASENAME: i64 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char indices of the matched characters in the text
+    pub positions: Vec<usize>,
+}
+
+/// Bonus of matching the character at every index of `text`
+fn position_bonuses(text: &[char], is_path: bool) -> Vec<i64> {
+    let basename_start = if is_path {
+        // Trailing slash doesn't start a new segment
+        let trimmed = text.len() - text.iter().rev().take_while(|c| **c == '/').count();
+        text[..trimmed]
+            .iter()
+            .rposition(|c| *c == '/')
+            .map(|idx| idx + 1)
+            .unwrap_or_default()
+    } else {
+        usize::MAX
+    };
+
+    text.iter()
+        .enumerate()
+        .map(|(idx, _)| {
+            let boundary = match idx.checked_sub(1).map(|prev| text[prev]) {
+                None | Some('/') => BONUS_SEGMENT,
+                Some(' ' | '-' | '_' | '.') => BONUS_BOUNDARY,
+                Some(_) => 0,
+            };
+            let basename = if idx >= basename_start {
+                BONUS_BASENAME
+            } else {
+                0
+            };
+            boundary + basename
+        })
+        .collect()
+}
+
+/// Find the best scoring way to match `pattern` as a subsequence of `text`.
+/// If `is_path` is true, `text` is treated as a path and matches in its basename get a bonus.
+/// Matching is case sensitive, so lowercase both if case doesn't matter
+pub fn fuzzy_match(pattern: &str, text: &str, is_path: bool) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    if pattern.len() > text.len() {
+        return None;
+    }
+
+    let bonuses = position_bonuses(&text, is_path);
+    // scores[j][i] is the best score of pattern[..=j] with pattern[j] matched at text[i],
+    // previous[j][i] is where pattern[j - 1] was matched for that score
+    let mut scores: Vec<Vec<Option<i64>>> = vec![vec![None; text.len()]; pattern.len()];
+    let mut previous: Vec<Vec<usize>> = vec![vec![0; text.len()]; pattern.len()];
+
+    for (i, c) in text.iter().enumerate() {
+        if *c == pattern[0] {
+            scores[0][i] = Some(SCORE_MATCH + bonuses[i]);
+        }
+    }
+
+    for j in 1..pattern.len() {
+        // Best (score + idx * PENALTY_GAP_EXTEND, idx) of the previous row before i - 1,
+        // so gap penalty can be calculated without going through the whole row
+        let mut best_gapped: Option<(i64, usize)> = None;
+        for i in 1..text.len() {
+            if i >= 2 {
+                if let Some(score) = scores[j - 1][i - 2] {
+                    let value = score + (i - 2) as i64 * PENALTY_GAP_EXTEND;
+                    if best_gapped.is_none_or(|(best, _)| value > best) {
+                        best_gapped = Some((value, i - 2));
+                    }
+                }
+            }
+
+            if text[i] != pattern[j] {
+                continue;
+            }
+
+            let consecutive = scores[j - 1][i - 1].map(|score| (score + BONUS_CONSECUTIVE, i - 1));
+            let gapped = best_gapped.map(|(value, k)| {
+                let score = value - (i - 2) as i64 * PENALTY_GAP_EXTEND - PENALTY_GAP_START;
+                (score, k)
+            });
+
+            let best = match (consecutive, gapped) {
+                (Some(c), Some(g)) => Some(if c.0 >= g.0 { c } else { g }),
+                (c, g) => c.or(g),
+            };
+
+            if let Some((score, k)) = best {
+                scores[j][i] = Some(score + SCORE_MATCH + bonuses[i]);
+                previous[j][i] = k;
+            }
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut idx, score) = scores[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(i, score)| score.map(|score| (i, score)))
+        // First of the best scores, so earlier matches win the ties
+        .fold(None, |best: Option<(usize, i64)>, (i, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((i, score)),
+        })?;
+
+    let mut positions = vec![0; pattern.len()];
+    for j in (0..pattern.len()).rev() {
+        positions[j] = idx;
+        idx = previous[j][idx];
+    }
+
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Find `pattern` as it is in `text`. Anchors require the match to be at the start
+/// and/or at the end of `text`. Scored like a fully contiguous fuzzy match
+pub fn literal_match(
+    pattern: &str,
+    text: &str,
+    anchor_start: bool,
+    anchor_end: bool,
+) -> Option<FuzzyMatch> {
+    let start = match (anchor_start, anchor_end) {
+        (true, true) => (text == pattern).then_some(0)?,
+        (true, false) => text.starts_with(pattern).then_some(0)?,
+        (false, true) => text.strip_suffix(pattern)?.len(),
+        (false, false) => text.find(pattern)?,
+    };
+
+    let start = text[..start].chars().count();
+    let len = pattern.chars().count() as i64;
+    Some(FuzzyMatch {
+        score: SCORE_MATCH * len + BONUS_CONSECUTIVE * (len - 1).max(0),
+        positions: (start..start + len as usize).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_match, literal_match};
+
+    #[test]
+    fn test_subsequence() {
+        assert!(fuzzy_match("pmgr", "/home/user/path-manager", true).is_some());
+        assert!(fuzzy_match("pmgrx", "/home/user/path-manager", true).is_none());
+        assert!(fuzzy_match("", "anything", false).is_some());
+        assert!(fuzzy_match("long pattern", "short", false).is_none());
+
+        let found = fuzzy_match("pmgr", "/home/user/path-manager", true).unwrap();
+        assert_eq!(found.positions, vec![11, 16, 20, 22]);
+    }
+
+    #[test]
+    fn test_positions_prefer_boundaries() {
+        // 'm' should be matched at the start of "manager", not in "home"
+        let found = fuzzy_match("man", "/home/path-manager", true).unwrap();
+        assert_eq!(found.positions, vec![11, 12, 13]);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let score = |pattern, text| fuzzy_match(pattern, text, true).unwrap().score;
+
+        // Contiguous beats scattered
+        assert!(score("proj", "/src/project") > score("proj", "/src/p-r-o-j"));
+        // Basename beats parent directories
+        assert!(score("api", "/work/billing/api") > score("api", "/work/api/billing"));
+        // Segment start beats middle of a word
+        assert!(score("app", "/src/app") > score("app", "/src/webapp"));
+    }
+
+    #[test]
+    fn test_literal_match() {
+        let found = literal_match("man", "/src/path-manager", false, false).unwrap();
+        assert_eq!(found.positions, vec![10, 11, 12]);
+        assert!(literal_match("/src", "/src/path-manager", true, false).is_some());
+        assert!(literal_match("path", "/src/path-manager", true, false).is_none());
+        assert!(literal_match("manager", "/src/path-manager", false, true).is_some());
+        assert!(literal_match("/src", "/src", true, true).is_some());
+        assert!(literal_match("/sr", "/src", true, true).is_none());
+    }
+}
@@ -0,0 +1,240 @@
+/// jumpers.rs parses the databases of other directory jumpers for the `import` mode
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::cli::Jumper;
+
+/// Directory from another jumper's database
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumperEntry {
+    pub path: String,
+    /// Visit count estimated from the jumper's score
+    pub visits: u32,
+    /// Unix time of the latest visit, 0 if the jumper doesn't record it
+    pub last_visited: u64,
+}
+
+/// `zoxide query --list --score` prints lines like `  12.5 /home/user/src`
+fn parse_zoxide(line: &str) -> Option<(&str, f64, u64)> {
+    let (score, path) = line.trim_start().split_once(' ')?;
+    Some((path, score.parse().ok()?, 0))
+}
+
+/// autojump.txt has lines like `22.36\t/home/user/src`
+fn parse_autojump(line: &str) -> Option<(&str, f64, u64)> {
+    let (weight, path) = line.split_once('\t')?;
+    let weight: f64 = weight.parse().ok()?;
+    // Every visit grows the weight w to sqrt(w^2 + 10^2)
+    Some((path, (weight / 10.0).powi(2), 0))
+}
+
+/// z and fasd have lines like `/home/user/src|12|1700000000`
+fn parse_z(line: &str) -> Option<(&str, f64, u64)> {
+    // Paths can contain '|' so split from the end
+    let mut fields = line.rsplitn(3, '|');
+    let time = fields.next()?.parse().ok()?;
+    let rank = fields.next()?.parse().ok()?;
+    Some((fields.next()?, rank, time))
+}
+
+/// `cd` commands with an absolute path in bash or zsh history. Zsh's extended
+/// history lines start with `: <time>:<duration>;`
+fn parse_history(line: &str) -> Option<(&str, f64, u64)> {
+    let (time, command) = match line.strip_prefix(": ") {
+        Some(extended) => {
+            let (meta, command) = extended.split_once(';')?;
+            let time = meta.split(':').next()?.parse().ok()?;
+            (time, command)
+        }
+        None => (0, line),
+    };
+    let path = command.trim().strip_prefix("cd ")?.trim();
+    let path = path
+        .strip_prefix(['"', '\''])
+        .and_then(|p| p.strip_suffix(['"', '\'']))
+        .unwrap_or(path);
+    Some((path, 1.0, time))
+}
+
+/// Parse the database of `jumper`. Lines that can't be parsed and relative paths
+/// are skipped and every path is only returned once
+pub fn parse(jumper: Jumper, text: &str) -> Vec<JumperEntry> {
+    let parse_line = match jumper {
+        Jumper::Zoxide => parse_zoxide,
+        Jumper::Autojump => parse_autojump,
+        Jumper::Z | Jumper::Fasd => parse_z,
+        Jumper::History => parse_history,
+    };
+    let home = env::var("HOME").ok();
+
+    let mut entries: Vec<JumperEntry> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (path, score, time) in text.lines().filter_map(parse_line) {
+        let path = match (path.strip_prefix('~'), &home) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+                format!("{home}{rest}")
+            }
+            _ => path.to_string(),
+        };
+        if !path.starts_with('/') {
+            continue;
+        }
+        let path = match path.trim_end_matches('/') {
+            "" => "/".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+
+        // History has one line per visit, databases one line per path
+        let visits = score.round().max(1.0) as u32;
+        match index.get(&path) {
+            Some(&i) => {
+                let entry = &mut entries[i];
+                entry.visits = entry.visits.saturating_add(visits);
+                entry.last_visited = entry.last_visited.max(time);
+            }
+            None => {
+                index.insert(path.clone(), entries.len());
+                entries.push(JumperEntry {
+                    path,
+                    visits,
+                    last_visited: time,
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+/// Where `jumper` keeps its database by default. Zoxide's database is binary so it's
+/// read with the `zoxide` command instead
+pub fn default_file(jumper: Jumper) -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let env_file = |name: &str| {
+        env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    match jumper {
+        Jumper::Zoxide => None,
+        Jumper::Autojump => env_file("XDG_DATA_HOME")
+            .or_else(|| Some(home()?.join(".local/share")))
+            .map(|dir| dir.join("autojump/autojump.txt")),
+        Jumper::Z => env_file("_Z_DATA").or_else(|| Some(home()?.join(".z"))),
+        Jumper::Fasd => env_file("_FASD_DATA").or_else(|| Some(home()?.join(".fasd"))),
+        // HISTFILE is rarely exported, so the history of the login shell is guessed
+        Jumper::History => env_file("HISTFILE").or_else(|| {
+            let home = home()?;
+            let zsh = env::var("SHELL").is_ok_and(|shell| shell.ends_with("zsh"));
+            let mut files = [".bash_history", ".zsh_history"];
+            if zsh {
+                files.reverse();
+            }
+            let files = files.map(|file| home.join(file));
+            let existing = files.iter().find(|file| file.exists());
+            Some(existing.unwrap_or(&files[0]).clone())
+        }),
+    }
+}
+
+/// Zsh stores some bytes in its history as 0x83 followed by the byte xor 0x20
+fn unmetafy(data: &[u8]) -> Vec<u8> {
+    let mut bytes = data.iter();
+    let mut unmetafied = Vec::with_capacity(data.len());
+    while let Some(&byte) = bytes.next() {
+        match (byte, bytes.clone().next()) {
+            (0x83, Some(&next)) => {
+                bytes.next();
+                unmetafied.push(next ^ 0x20);
+            }
+            _ => unmetafied.push(byte),
+        }
+    }
+    unmetafied
+}
+
+/// Turn a database into text. Invalid UTF-8 is replaced and zsh history that isn't
+/// valid UTF-8 is unmetafied first
+pub fn decode(jumper: Jumper, data: Vec<u8>) -> String {
+    match String::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) if jumper == Jumper::History => {
+            String::from_utf8_lossy(&unmetafy(e.as_bytes())).into_owned()
+        }
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    }
+}
+
+/// Name for an imported path: its last component
+pub fn entry_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map_or(path.into(), |name| name.to_string_lossy().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, visits: u32, last_visited: u64) -> JumperEntry {
+        JumperEntry {
+            path: path.into(),
+            visits,
+            last_visited,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(Jumper::Zoxide, "  12.4 /src/a b\n   0.2 /tmp/\nbroken\n"),
+            vec![entry("/src/a b", 12, 0), entry("/tmp", 1, 0)]
+        );
+        assert_eq!(
+            parse(
+                Jumper::Autojump,
+                "31.6\t/src\n10.0\t/tmp\nnot a number\t/x\n"
+            ),
+            vec![entry("/src", 10, 0), entry("/tmp", 1, 0)]
+        );
+        assert_eq!(
+            parse(
+                Jumper::Z,
+                "/src|4|1700000000\n/a|b|3|1700000001\nrelative|1|1\n"
+            ),
+            vec![entry("/src", 4, 1700000000), entry("/a|b", 3, 1700000001)]
+        );
+        assert_eq!(
+            parse(
+                Jumper::History,
+                "cd /src\nls\ncd ..\n: 1700000000:0;cd '/tmp/x y'\n: 1700000005:0;cd /src/\n"
+            ),
+            vec![
+                entry("/src", 2, 1700000005),
+                entry("/tmp/x y", 1, 1700000000)
+            ]
+        );
+        assert_eq!(parse(Jumper::Fasd, "/|1.5|1\n"), vec![entry("/", 2, 1)]);
+    }
+
+    #[test]
+    fn test_decode() {
+        // "cd /tmp/ă" with the 0x83 byte of ă metafied by zsh
+        let zsh = b"cd /tmp/\xc4\x83\xa3\ncd /src\n".to_vec();
+        assert_eq!(decode(Jumper::History, zsh), "cd /tmp/ă\ncd /src\n");
+        assert_eq!(
+            decode(Jumper::Z, b"/a\xff|1|1\n".to_vec()),
+            "/a\u{fffd}|1|1\n"
+        );
+        assert_eq!(decode(Jumper::History, "cd /ă".into()), "cd /ă");
+    }
+
+    #[test]
+    fn test_entry_name() {
+        assert_eq!(entry_name("/home/user/src"), "src");
+        assert_eq!(entry_name("/"), "/");
+    }
+}
@@ -1,38 +1,134 @@
 use clap::Parser;
 use search_tui::{run_edit_tui, run_select_tui};
 use std::{
-    fs,
-    io::{self, BufRead, Write},
+    env, fs,
+    io::{self, BufRead, IsTerminal, Write},
+    mem::ManuallyDrop,
+    os::fd::FromRawFd,
+    path::Path,
+    process::Command,
 };
 
 mod cli;
 mod config_path;
+mod error;
+mod exchange;
+mod fuzzy;
+mod jumpers;
+mod migrations;
+mod output;
 mod paths;
+mod query;
 mod search_tui;
+mod settings;
+mod shell_init;
 mod tmux;
-use cli::{Args, Mode};
+use cli::{Args, BackupAction, ConfigAction, ExchangeFormat, Jumper, Mode, Overwrite};
 use tmux::Tmux;
 
-use crate::config_path::{load_saved_paths, save_paths};
+use crate::{
+    config_path::{locate_settings, Catalog},
+    error::{Error, Result},
+    paths::{unix_time, PathItem, PathItems, PathsDiff, SearchOptions, SortOrder},
+    settings::{Settings, DEFAULT_SETTINGS},
+};
+
+/// Print `prompt` and read a trimmed line from stdin
+fn prompt_line(prompt: &str) -> Result<String> {
+    print!("{prompt}");
+    let mut buf = String::new();
+    io::stdout()
+        .flush()
+        .and_then(|_| io::stdin().lock().read_line(&mut buf))
+        .map_err(|e| Error::io("Cannot read the answer", e))?;
+    Ok(buf.trim().into())
+}
+
+/// Ask a yes/no question. Only an answer starting with 'y' is a yes
+fn confirm(prompt: &str) -> Result<bool> {
+    Ok(prompt_line(&format!("{prompt} (y/n): "))?
+        .to_lowercase()
+        .starts_with('y'))
+}
 
-fn add_path(path: &str) {
-    let path: String = fs::canonicalize(path)
-        .unwrap_or_else(|_| panic!("Path '{path}' was not found"))
-        .to_str()
-        .unwrap()
-        .into();
+/// Ask for a confirmation unless `yes` is given. Without a terminal there's nobody to ask,
+/// so the action is refused
+fn confirm_or_abort(yes: bool, prompt: &str, refusal: &str) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(Error::Aborted(Some(format!(
+            "Refusing to {refusal} without confirmation. Use --yes to confirm"
+        ))));
+    }
 
-    let stdin = io::stdin();
-    let mut paths = load_saved_paths();
+    if !confirm(prompt)? {
+        return Err(Error::Aborted(None));
+    }
+    Ok(())
+}
+
+/// Use `value` if it's given, otherwise ask it from the user if stdin is interactive.
+/// `fallback` is used when there's no one to ask
+fn value_or_prompt(
+    value: Option<&str>,
+    prompt: &str,
+    interactive: bool,
+    fallback: &str,
+) -> Result<String> {
+    match value {
+        Some(value) => Ok(value.trim().into()),
+        None if interactive => prompt_line(prompt),
+        None => Ok(fallback.into()),
+    }
+}
+
+/// Canonical form of `path`, which has to exist
+fn canonical_path(path: &str) -> Result<String> {
+    let canonical = fs::canonicalize(path)
+        .map_err(|_| Error::NotFound(format!("Path '{path}' was not found")))?;
+    match canonical.to_str() {
+        Some(canonical) => Ok(canonical.into()),
+        None => Err(Error::Validation(format!(
+            "Path '{}' is not valid UTF-8",
+            canonical.display()
+        ))),
+    }
+}
+
+fn add_path(
+    catalog: &Catalog,
+    path: &str,
+    name: Option<&str>,
+    description: Option<&str>,
+    overwrite: Overwrite,
+    tags: &[String],
+) -> Result<()> {
+    let path = canonical_path(path)?;
+    let interactive = io::stdin().is_terminal();
+    let paths = catalog.load()?;
     let existing = paths.exists(&path);
 
     if existing {
-        print!("Path '{path}' already exists.\nWant to override (y/n): ");
-        io::stdout().flush().unwrap();
-        let mut answer = String::new();
-        let _ = stdin.lock().read_line(&mut answer).unwrap();
-        if answer.trim().to_lowercase().starts_with('n') {
-            std::process::exit(0);
+        let overwrite = match overwrite {
+            Overwrite::Always => true,
+            Overwrite::Never => false,
+            // Without a terminal there's nobody to answer, so keep the old entry
+            Overwrite::Ask if !interactive => false,
+            Overwrite::Ask => {
+                let answer = prompt_line(&format!(
+                    "Path '{path}' already exists.\nWant to override (y/n): "
+                ))?;
+                !answer.to_lowercase().starts_with('n')
+            }
+        };
+
+        if !overwrite {
+            return Err(Error::AlreadyExists(format!(
+                "Path '{path}' already exists"
+            )));
         }
     }
 
@@ -42,53 +138,643 @@ fn add_path(path: &str) {
         println!("Adding path: {path}");
     }
 
-    print!("Path name: ");
-    io::stdout().flush().unwrap();
-    let mut name_buf = String::new();
-    {
-        let _ = stdin.lock().read_line(&mut name_buf).unwrap();
+    // Overriding without a terminal keeps the values that weren't given
+    let (old_name, old_description) = match paths.get(&path) {
+        Some(old) => (old.name.as_str(), old.description.as_str()),
+        None => ("", ""),
+    };
+    let name = value_or_prompt(name, "Path name: ", interactive, old_name)?;
+    let description = value_or_prompt(
+        description,
+        "Path description: ",
+        interactive,
+        old_description,
+    )?;
+
+    let mut new_path = PathItem::new(name, path.trim().into(), description);
+    catalog.update(|paths| {
+        // Overriding only replaces what the user can give, usage and tags are kept
+        if let Some(old) = paths.get(&path) {
+            new_path.keep_saved_data(old);
+            new_path.tags = old.tags.clone();
+        }
+        for tag in tags {
+            new_path.add_tag(tag);
+        }
+
+        paths.add_path(new_path);
+        Ok(true)
+    })
+}
+
+/// Turn `path` into the form it's saved in the catalog.
+/// Paths of removed directories are used as is so they can still be found from the catalog
+fn saved_path(path: &str) -> String {
+    canonical_path(path).unwrap_or_else(|_| path.trim_end_matches('/').into())
+}
+
+fn edit_path(
+    catalog: &Catalog,
+    path: &str,
+    name: Option<&str>,
+    description: Option<&str>,
+    move_to: Option<&str>,
+    tags: &[String],
+    untags: &[String],
+) -> Result<()> {
+    let path = saved_path(path);
+    let move_to = move_to.map(canonical_path).transpose()?;
+
+    catalog.update(|paths| {
+        if !paths.exists(&path) {
+            return Err(Error::NotFound(format!("Path '{path}' is not saved")));
+        }
+
+        if let Some(new_path) = &move_to {
+            if *new_path != path && paths.exists(new_path) {
+                return Err(Error::AlreadyExists(format!(
+                    "Path '{new_path}' already exists"
+                )));
+            }
+        }
+
+        // Existence is checked above
+        let item = paths.get_mut(&path).unwrap();
+        if let Some(name) = name {
+            item.set_name(name.trim().into());
+        }
+        if let Some(description) = description {
+            item.set_description(description.trim().into());
+        }
+        for tag in untags {
+            if !item.remove_tag(tag) {
+                eprintln!("Path '{path}' doesn't have tag '{tag}'");
+            }
+        }
+        for tag in tags {
+            item.add_tag(tag);
+        }
+        if let Some(new_path) = move_to {
+            println!("Moving path: {path} -> {new_path}");
+            item.set_full_path(new_path);
+        }
+
+        Ok(true)
+    })
+}
+
+fn remove_paths(
+    catalog: &Catalog,
+    path: Option<&str>,
+    name: Option<&str>,
+    query: Option<&str>,
+    yes: bool,
+    dry_run: bool,
+    search: SearchOptions,
+) -> Result<()> {
+    let mut paths = catalog.load()?;
+    paths.sort(SortOrder::Path);
+
+    let targets: Vec<String> = if let Some(path) = path {
+        // Removed directories can still be removed from the catalog
+        let path = saved_path(path);
+        paths
+            .get(&path)
+            .map(|p| p.full_path.clone())
+            .into_iter()
+            .collect()
+    } else if let Some(name) = name {
+        paths
+            .find_by_name(name)
+            .iter()
+            .map(|p| p.full_path.clone())
+            .collect()
+    } else {
+        let query = query.unwrap_or_default();
+        paths
+            .filter(query, search)
+            .iter()
+            .map(|p| p.full_path.clone())
+            .collect()
+    };
+
+    if targets.is_empty() {
+        return Err(Error::NotFound("No saved paths matched".into()));
+    }
+
+    if dry_run {
+        println!("Would remove:");
+    } else {
+        println!("Removing:");
+    }
+    for target in &targets {
+        println!("  {target}");
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    confirm_or_abort(
+        yes,
+        &format!("Remove {} path(s)?", targets.len()),
+        "remove paths",
+    )?;
+
+    catalog.update(|paths| {
+        let mut changed = false;
+        for target in &targets {
+            changed |= paths.remove(target);
+        }
+        Ok(changed)
+    })
+}
+
+/// Replace the catalog with an empty one after backing it up
+fn reset_catalog(catalog: &Catalog, yes: bool) -> Result<()> {
+    confirm_or_abort(yes, "Remove all saved paths?", "reset the catalog")?;
+
+    match catalog.reset()? {
+        Some(backup) => println!("Old catalog is saved in {}", backup.display()),
+        None => println!("Created an empty catalog"),
+    }
+    Ok(())
+}
+
+/// Human readable age of a backup made at `millis`
+fn backup_age(millis: u64) -> String {
+    let seconds = unix_time().saturating_sub(millis / 1000);
+    match seconds {
+        0..=59 => "just now".into(),
+        60..=3599 => format!("{} minutes ago", seconds / 60),
+        3600..=86399 => format!("{} hours ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+fn backup_command(catalog: &Catalog, action: &BackupAction) -> Result<()> {
+    match action {
+        BackupAction::List => {
+            let backups = catalog.backups()?;
+            if backups.is_empty() {
+                println!("No backups");
+                return Ok(());
+            }
+            println!("{:13}  {:15}  PATHS", "ID", "SAVED");
+            for backup in backups {
+                // Backups are listed even if they can't be read
+                let paths = catalog
+                    .load_backup(backup.id)
+                    .map_or("?".into(), |items| items.paths.len().to_string());
+                println!("{:13}  {:15}  {paths}", backup.id, backup_age(backup.id));
+            }
+        }
+        BackupAction::Diff { id } => {
+            let backup = catalog.load_backup(*id)?;
+            let current = catalog.load()?;
+            let diff = backup.diff(&current);
+            if diff.is_empty() {
+                println!("No changes since backup {id}");
+            }
+            print!("{}", output::format_diff(&diff));
+        }
+        BackupAction::Restore { id, yes } => {
+            let backup = catalog.load_backup(*id)?;
+            let current = catalog.load()?;
+            let diff = current.diff(&backup);
+            if diff.is_empty() {
+                println!("Catalog is already the same as backup {id}");
+                return Ok(());
+            }
+            print!("{}", output::format_diff(&diff));
+            confirm_or_abort(*yes, "Restore the backup?", "restore the backup")?;
+            catalog.restore(*id)?;
+            println!("Restored backup {id}");
+        }
     }
-    print!("Path description: ");
-    io::stdout().flush().unwrap();
-    let mut description_buf = String::new();
-    {
-        let _ = stdin.lock().read_line(&mut description_buf).unwrap();
+    Ok(())
+}
+
+/// Read the database of `jumper` from `file` or from the jumper's default location
+fn read_jumper_database(jumper: Jumper, file: Option<&Path>) -> Result<String> {
+    // Databases aren't always valid UTF-8, so one odd path doesn't stop the import
+    let read = |file: &Path| {
+        fs::read(file)
+            .map(|data| jumpers::decode(jumper, data))
+            .map_err(|e| Error::io(format!("Cannot read {}", file.display()), e))
+    };
+    match (file, jumpers::default_file(jumper)) {
+        (Some(file), _) => read(file),
+        (None, Some(file)) => read(&file),
+        (None, None) if jumper == Jumper::Zoxide => {
+            let output = Command::new("zoxide")
+                .args(["query", "--list", "--score"])
+                .output()
+                .map_err(|e| Error::io("Cannot run zoxide", e))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(Error::io(
+                    "zoxide failed",
+                    io::Error::other(stderr.trim().to_string()),
+                ));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into())
+        }
+        (None, None) => Err(Error::Config(
+            "Cannot find the database to import, give the file as an argument".into(),
+        )),
     }
+}
+
+/// Save the directories `jumper` has recorded. Directories that are already saved
+/// or don't exist anymore are skipped
+fn import_paths(
+    catalog: &Catalog,
+    jumper: Jumper,
+    file: Option<&Path>,
+    dry_run: bool,
+) -> Result<()> {
+    let entries = jumpers::parse(jumper, &read_jumper_database(jumper, file)?);
+    let (existing, missing): (Vec<_>, Vec<_>) = entries
+        .iter()
+        .partition(|entry| Path::new(&entry.path).is_dir());
 
-    let new_path = paths::PathItem::new(
-        name_buf.trim().into(),
-        path.trim().into(),
-        description_buf.trim().into(),
+    // Returns how many paths were added and how many were already saved
+    let import = |paths: &mut PathItems| {
+        let before = paths.clone();
+        let mut saved = 0;
+        for entry in &existing {
+            if paths.exists(&entry.path) {
+                saved += 1;
+                continue;
+            }
+            let name = jumpers::entry_name(&entry.path);
+            let mut item = PathItem::new(name, entry.path.clone(), String::new());
+            item.visits = entry.visits;
+            item.last_visited = entry.last_visited;
+            paths.add_path(item);
+        }
+        print!("{}", output::format_diff(&before.diff(paths)));
+        (existing.len() - saved, saved)
+    };
+
+    let (added, saved) = if dry_run {
+        import(&mut catalog.load_read_only()?)
+    } else {
+        let mut counts = (0, 0);
+        catalog.update(|paths| {
+            counts = import(paths);
+            Ok(counts.0 > 0)
+        })?;
+        counts
+    };
+
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    println!(
+        "{verb} {added} paths. Skipped {saved} already saved paths and {} directories that don't exist",
+        missing.len()
     );
+    Ok(())
+}
 
-    paths.add_path(new_path);
-    save_paths(paths);
+/// Save paths exported with `export`. Imported paths that are already saved with
+/// other details conflict and are kept as they are unless `replace` is given
+fn import_exchange(
+    catalog: &Catalog,
+    format: ExchangeFormat,
+    file: Option<&Path>,
+    replace: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let text = match file {
+        Some(file) => fs::read_to_string(file)
+            .map_err(|e| Error::io(format!("Cannot read {}", file.display()), e))?,
+        None => io::read_to_string(io::stdin()).map_err(|e| Error::io("Cannot read stdin", e))?,
+    };
+    let imported = exchange::import(&text, format)?;
+
+    // Returns how many paths were added and replaced, and the saved and imported
+    // versions of the conflicting paths
+    let import = |paths: &mut PathItems| {
+        let before = paths.clone();
+        let mut conflicts = Vec::new();
+        for item in &imported {
+            let mut item = item.clone();
+            match paths.get(&item.full_path) {
+                Some(saved) if saved.same_details(&item) => continue,
+                Some(saved) if !replace => {
+                    conflicts.push((saved.clone(), item));
+                    continue;
+                }
+                // Usage statistics are local and kept
+                Some(saved) => item.keep_saved_data(saved),
+                None => {}
+            }
+            paths.add_path(item);
+        }
+        let diff = before.diff(paths);
+        print!("{}", output::format_diff(&diff));
+        (diff.added.len(), diff.changed.len(), conflicts)
+    };
+
+    let (added, replaced, conflicts) = if dry_run {
+        import(&mut catalog.load_read_only()?)
+    } else {
+        let mut result = (0, 0, Vec::new());
+        catalog.update(|paths| {
+            result = import(paths);
+            Ok(result.0 + result.1 > 0)
+        })?;
+        result
+    };
+
+    if !conflicts.is_empty() {
+        println!(
+            "Kept {} saved paths that conflict with imported paths. Use --replace to replace them:",
+            conflicts.len()
+        );
+        let conflicts = PathsDiff {
+            changed: conflicts.iter().map(|(saved, new)| (saved, new)).collect(),
+            ..Default::default()
+        };
+        print!("{}", output::format_diff(&conflicts));
+    }
+    let (import_verb, replace_verb) = if dry_run {
+        ("Would import", "replace")
+    } else {
+        ("Imported", "replaced")
+    };
+    println!("{import_verb} {added} new paths and {replace_verb} {replaced} paths");
+    Ok(())
 }
 
-fn main() {
-    let args = Args::parse();
+/// Record a visit into `path` if it's saved. The path has already been printed or
+/// jumped into, so a failure is only reported
+fn record_visit(catalog: &Catalog, path: &str) {
+    if let Err(e) = catalog.update(|paths| Ok(paths.visit(path))) {
+        eprintln!("Couldn't record the visit: {e}");
+    }
+}
+
+/// Check that `fd` is a descriptor the shell opened for us, like `3>file`
+fn check_output_fd(fd: i32) -> Result<()> {
+    if fd <= 2 {
+        return Err(Error::Validation(
+            "--output-fd has to be 3 or more, 0-2 are the standard streams".into(),
+        ));
+    }
+    // /dev/fd has an entry for every descriptor open in this process
+    if fs::symlink_metadata(format!("/dev/fd/{fd}")).is_err() {
+        return Err(Error::Validation(format!(
+            "File descriptor {fd} isn't open"
+        )));
+    }
+    Ok(())
+}
+
+/// Write `path` into an already open file descriptor, like `3>file` in shell
+fn write_to_fd(fd: i32, path: &str) -> Result<()> {
+    check_output_fd(fd)?;
+
+    // SAFETY: fd was checked above to be open and not one of our stdio streams.
+    // It's owned by the shell that started us, so ManuallyDrop keeps us from closing it
+    let mut output = ManuallyDrop::new(unsafe { fs::File::from_raw_fd(fd) });
+    writeln!(output, "{path}").map_err(|e| Error::io(format!("Cannot write to fd {fd}"), e))
+}
+
+/// Open the settings file in the user's editor and check it after editing.
+/// A commented default settings file is created if there isn't one
+fn edit_settings(file: &Path) -> Result<()> {
+    if !file.exists() {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| Error::io(format!("Cannot create {}", dir.display()), e))?;
+        }
+        fs::write(file, DEFAULT_SETTINGS)
+            .map_err(|e| Error::io(format!("Cannot create {}", file.display()), e))?;
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    // Editors like `code --wait` need their arguments
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(file)
+        .status()
+        .map_err(|e| Error::io(format!("Cannot run {program}"), e))?;
+    if !status.success() {
+        return Err(Error::Aborted(Some(format!("{program} failed"))));
+    }
+
+    Settings::load(file)?;
+    Ok(())
+}
+
+fn config_command(action: &ConfigAction) -> Result<()> {
+    let file = locate_settings()?;
+    match action {
+        ConfigAction::Show => {
+            let settings = Settings::load(&file)?;
+            println!("# {}", file.display());
+            print!("{}", settings.to_toml());
+        }
+        ConfigAction::Edit => edit_settings(&file)?,
+        ConfigAction::Path => println!("{}", file.display()),
+    }
+    Ok(())
+}
+
+/// TUI errors come from several libraries, so they are only reported as text
+fn tui_error(error: Box<dyn std::error::Error>) -> Error {
+    Error::io("TUI failed", io::Error::other(error.to_string()))
+}
+
+fn run(args: &Args) -> Result<()> {
+    // Broken settings can still be inspected and fixed, and shells started with
+    // `eval "$(path-manager init bash)"` don't depend on the settings
+    match args.mode() {
+        Mode::Config { action } => return config_command(action),
+        Mode::Init {
+            shell,
+            cmd,
+            no_bind,
+        } => {
+            print!("{}", shell_init::init_script(*shell, cmd, !no_bind));
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let settings = match locate_settings().and_then(|file| Settings::load(&file)) {
+        Ok(settings) => settings,
+        // Resetting is how a broken setup is recovered
+        Err(e) if matches!(args.mode(), Mode::Reset { .. }) => {
+            eprintln!("{e}\nUsing the default settings");
+            Settings::default()
+        }
+        Err(e) => return Err(e),
+    };
+    let search = args.search_options(&settings.search);
+    let sort = settings.search.sort;
+    let catalog = Catalog::locate(args.catalog(), &settings.storage)?;
     match args.mode() {
         Mode::Pwd { path } => {
-            let tmux = Tmux::new().init();
+            let tmux = Tmux::new(&settings.tmux.cd_command).init()?;
             if let Some(path) = path {
-                tmux.save_pwd(path)
-            } else {
-                tmux.cd_pwd()
+                tmux.save_pwd(&canonical_path(path)?)?;
+            } else if let Some(path) = tmux.cd_pwd()? {
+                record_visit(&catalog, &path);
             }
         }
-        Mode::AddPath { path } => add_path(path),
-        Mode::Tui { edit, input } => {
-            let mut items = load_saved_paths();
-            items.sort();
+        Mode::AddPath {
+            path,
+            name,
+            description,
+            force,
+            no_overwrite,
+            tags,
+        } => {
+            let overwrite = if *force {
+                Overwrite::Always
+            } else if *no_overwrite {
+                Overwrite::Never
+            } else {
+                Overwrite::Ask
+            };
+            add_path(
+                &catalog,
+                path,
+                name.as_deref(),
+                description.as_deref(),
+                overwrite,
+                tags,
+            )?
+        }
+        Mode::Remove {
+            path,
+            name,
+            query,
+            yes,
+            dry_run,
+        } => remove_paths(
+            &catalog,
+            path.as_deref(),
+            name.as_deref(),
+            query.as_deref(),
+            *yes,
+            *dry_run,
+            search,
+        )?,
+        Mode::Edit {
+            path,
+            name,
+            description,
+            move_to,
+            tags,
+            untags,
+        } => edit_path(
+            &catalog,
+            path,
+            name.as_deref(),
+            description.as_deref(),
+            move_to.as_deref(),
+            tags,
+            untags,
+        )?,
+        Mode::List { format, input } => {
+            let mut items = catalog.load()?;
+            items.sort(sort);
+            let filtered = items.filter(&input.join(" "), search);
+            print!("{}", output::format_paths(&filtered, *format));
+        }
+        Mode::Query { input } => {
+            let mut items = catalog.load()?;
+            items.sort(sort);
+            let query = input.join(" ");
+            let path = match items.filter(&query, search).first() {
+                Some(path) => path.full_path.clone(),
+                None => return Err(Error::NotFound(format!("No path matches '{query}'"))),
+            };
+            println!("{path}");
+            record_visit(&catalog, &path);
+        }
+        Mode::Reset { yes } => reset_catalog(&catalog, *yes)?,
+        Mode::Backup { action } => backup_command(&catalog, action)?,
+        Mode::Export { format, input } => {
+            let mut items = catalog.load()?;
+            items.sort(sort);
+            let filtered = items.filter(&input.join(" "), search);
+            print!("{}", exchange::export(&filtered, *format));
+        }
+        Mode::Import {
+            from: Some(jumper),
+            file,
+            dry_run,
+            ..
+        } => import_paths(&catalog, *jumper, file.as_deref(), *dry_run)?,
+        Mode::Import {
+            from: None,
+            format,
+            file,
+            replace,
+            dry_run,
+            ..
+        } => import_exchange(&catalog, *format, file.as_deref(), *replace, *dry_run)?,
+        // Handled before the settings are loaded
+        Mode::Config { .. } | Mode::Init { .. } => {}
+        Mode::Tui {
+            edit,
+            print,
+            output_fd,
+            input,
+        } => {
+            // Fail before the user picks a path
+            if let Some(fd) = output_fd {
+                check_output_fd(*fd)?;
+            }
+            let mut items = catalog.load()?;
+            items.sort(sort);
             if *edit {
-                if let Ok(Some(items)) = run_edit_tui(&items, input) {
-                    save_paths(items);
+                if let Some(edited) = run_edit_tui(&items, input, search, settings.tui.start_mode)
+                    .map_err(tui_error)?
+                {
+                    catalog.update(|current| Ok(current.merge_changes(&items, &edited)))?;
+                }
+            } else if let Some(path) = run_select_tui(
+                &items,
+                input,
+                *print || output_fd.is_some(),
+                search,
+                settings.tui.start_mode,
+            )
+            .map_err(tui_error)?
+            {
+                if *print {
+                    println!("{}", path.full_path);
+                } else if let Some(fd) = output_fd {
+                    write_to_fd(*fd, &path.full_path)?;
+                } else {
+                    let tmux = Tmux::new(&settings.tmux.cd_command);
+                    let tmux = tmux.init()?;
+                    tmux.cd_into(&path.full_path)?;
                 }
-            } else if let Ok(Some(path)) = run_select_tui(&items, input) {
-                let tmux = Tmux::new();
-                let tmux = tmux.init();
-                tmux.cd_into(&path.full_path);
+                record_visit(&catalog, &path.full_path);
             }
         }
     };
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(error) = run(&args) {
+        let message = error.to_string();
+        if !message.is_empty() {
+            eprintln!("{message}");
+        }
+        std::process::exit(error.exit_code());
+    }
 }
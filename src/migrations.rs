@@ -0,0 +1,110 @@
+//! Upgrades of older catalog files to the current format.
+//!
+//! Every change to the file format bumps [`CATALOG_VERSION`] and adds a migration
+//! from the previous version to [`MIGRATIONS`]. Migrations work on the raw JSON so
+//! they don't depend on the current structs
+
+use serde_json::{json, Value};
+
+/// Version of the catalog files this program writes
+pub const CATALOG_VERSION: u64 = 2;
+/// Files from before the version field was added
+const UNVERSIONED: u64 = 1;
+
+/// `MIGRATIONS[n]` upgrades a file of version `n + 1` to version `n + 2`
+const MIGRATIONS: [fn(&mut Value); (CATALOG_VERSION - UNVERSIONED) as usize] = [v1_to_v2];
+
+/// Why a catalog file can't be read
+#[derive(Debug)]
+pub enum FormatError {
+    /// File isn't valid JSON or doesn't look like a catalog
+    Syntax(serde_json::Error),
+    /// File was written by a newer version of the program
+    NewerVersion(u64),
+}
+
+/// Version 1 didn't have usage statistics or tags in the first releases
+fn v1_to_v2(catalog: &mut Value) {
+    let paths = catalog
+        .get_mut("paths")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten();
+    for path in paths.filter_map(Value::as_object_mut) {
+        path.entry("visits").or_insert(json!(0));
+        path.entry("last_visited").or_insert(json!(0));
+        path.entry("tags").or_insert(json!([]));
+    }
+}
+
+/// Upgrade `catalog` to [`CATALOG_VERSION`]. Returns the version the catalog was in
+pub fn migrate(catalog: &mut Value) -> Result<u64, FormatError> {
+    let invalid = |message: &str| FormatError::Syntax(serde::de::Error::custom(message));
+
+    let Some(object) = catalog.as_object_mut() else {
+        return Err(invalid("catalog has to be a JSON object"));
+    };
+    let version = match object.get("version") {
+        None => UNVERSIONED,
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version >= UNVERSIONED)
+            .ok_or_else(|| invalid("version has to be a positive number"))?,
+    };
+    if version > CATALOG_VERSION {
+        return Err(FormatError::NewerVersion(version));
+    }
+
+    object.insert("version".into(), json!(CATALOG_VERSION));
+    for migration in &MIGRATIONS[(version - UNVERSIONED) as usize..] {
+        migration(catalog);
+    }
+
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let mut catalog = json!({
+            "paths": [{"name": "a", "full_path": "/a", "description": "", "color": "red"}]
+        });
+        assert_eq!(migrate(&mut catalog).unwrap(), 1);
+        assert_eq!(
+            catalog,
+            json!({
+                "version": 2,
+                "paths": [{
+                    "name": "a",
+                    "full_path": "/a",
+                    "description": "",
+                    "color": "red",
+                    "visits": 0,
+                    "last_visited": 0,
+                    "tags": []
+                }]
+            })
+        );
+
+        // Current version is left as is
+        let migrated = catalog.clone();
+        assert_eq!(migrate(&mut catalog).unwrap(), CATALOG_VERSION);
+        assert_eq!(catalog, migrated);
+
+        assert!(matches!(
+            migrate(&mut json!({"version": 99, "paths": []})),
+            Err(FormatError::NewerVersion(99))
+        ));
+        assert!(matches!(
+            migrate(&mut json!({"version": "2"})),
+            Err(FormatError::Syntax(_))
+        ));
+        assert!(matches!(
+            migrate(&mut json!([])),
+            Err(FormatError::Syntax(_))
+        ));
+    }
+}
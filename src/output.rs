@@ -0,0 +1,170 @@
+/// output.rs contains functions to print PathItems for other programs and humans
+use crate::{
+    cli::ListFormat,
+    paths::{PathItem, PathsDiff},
+};
+
+/// Replace characters that would break the TSV structure
+fn tsv_field(field: &str) -> String {
+    field.replace(['\t', '\n', '\r'], " ")
+}
+
+fn format_table(items: &[&PathItem]) -> String {
+    let header = ("NAME", "PATH", "TAGS", "DESCRIPTION");
+    let name_width = items
+        .iter()
+        .map(|p| p.name.chars().count())
+        .chain([header.0.len()])
+        .max()
+        .unwrap_or_default();
+    let path_width = items
+        .iter()
+        .map(|p| p.full_path.chars().count())
+        .chain([header.1.len()])
+        .max()
+        .unwrap_or_default();
+    let tags: Vec<String> = items.iter().map(|p| p.tags.join(",")).collect();
+    let tags_width = tags
+        .iter()
+        .map(|t| t.chars().count())
+        .chain([header.2.len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut lines = vec![format!(
+        "{:name_width$}  {:path_width$}  {:tags_width$}  {}",
+        header.0, header.1, header.2, header.3
+    )];
+    for (item, tags) in items.iter().zip(tags) {
+        lines.push(format!(
+            "{:name_width$}  {:path_width$}  {:tags_width$}  {}",
+            item.name, item.full_path, tags, item.description
+        ));
+    }
+
+    lines
+        .into_iter()
+        .map(|line| line.trim_end().to_string() + "\n")
+        .collect()
+}
+
+/// Format `items` in given `format`. Every format ends with a newline
+pub fn format_paths(items: &[&PathItem], format: ListFormat) -> String {
+    match format {
+        ListFormat::Table => format_table(items),
+        ListFormat::Tsv => items
+            .iter()
+            .map(|p| {
+                format!(
+                    "{}\t{}\t{}\t{}\n",
+                    tsv_field(&p.name),
+                    tsv_field(&p.full_path),
+                    tsv_field(&p.description),
+                    tsv_field(&p.tags.join(","))
+                )
+            })
+            .collect(),
+        ListFormat::Paths => items.iter().map(|p| format!("{}\n", p.full_path)).collect(),
+        ListFormat::Json => serde_json::to_string_pretty(items).unwrap() + "\n",
+    }
+}
+
+/// Format `diff` with a line for every added (+), removed (-) and changed (~) path
+/// and an indented line for every changed field
+pub fn format_diff(diff: &PathsDiff) -> String {
+    let mut lines = Vec::new();
+    for item in &diff.added {
+        lines.push(format!("+ {}  {}", item.full_path, item.name));
+    }
+    for item in &diff.removed {
+        lines.push(format!("- {}  {}", item.full_path, item.name));
+    }
+    for (old, new) in &diff.changed {
+        lines.push(format!("~ {}", new.full_path));
+        let fields = [
+            ("name", old.name.clone(), new.name.clone()),
+            (
+                "description",
+                old.description.clone(),
+                new.description.clone(),
+            ),
+            ("tags", old.tags.join(","), new.tags.join(",")),
+        ];
+        for (field, old, new) in fields {
+            if old != new {
+                lines.push(format!("    {field}: {old:?} -> {new:?}"));
+            }
+        }
+    }
+
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_diff, format_paths};
+    use crate::{
+        cli::ListFormat,
+        paths::{PathItem, PathItems},
+    };
+
+    fn items() -> Vec<PathItem> {
+        let mut home = PathItem::new("Home".into(), "/home/user".into(), "User's home".into());
+        home.add_tag("me");
+        home.add_tag("dotfiles");
+        vec![
+            home,
+            PathItem::new("Tabs".into(), "/tmp/tabs".into(), "With\ttab".into()),
+        ]
+    }
+
+    #[test]
+    fn test_formats() {
+        let items = items();
+        let items: Vec<&PathItem> = items.iter().collect();
+
+        assert_eq!(
+            format_paths(&items, ListFormat::Table),
+            "NAME  PATH        TAGS         DESCRIPTION\n\
+             Home  /home/user  me,dotfiles  User's home\n\
+             Tabs  /tmp/tabs                With\ttab\n"
+        );
+        assert_eq!(
+            format_paths(&items, ListFormat::Tsv),
+            "Home\t/home/user\tUser's home\tme,dotfiles\nTabs\t/tmp/tabs\tWith tab\t\n"
+        );
+        assert_eq!(
+            format_paths(&items, ListFormat::Paths),
+            "/home/user\n/tmp/tabs\n"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_paths(&items, ListFormat::Json)).unwrap();
+        assert_eq!(json[0]["full_path"], "/home/user");
+        assert_eq!(json[1]["name"], "Tabs");
+        assert!(json[0].get("lname").is_none());
+        assert_eq!(json[0]["tags"][1], "dotfiles");
+    }
+
+    #[test]
+    fn test_format_diff() {
+        let mut old = PathItems::default();
+        old.paths = items();
+        let mut new = old.clone();
+        new.paths[0].set_name("House".into());
+        new.paths[0].add_tag("new");
+        new.paths.remove(1);
+        new.paths
+            .push(PathItem::new("Src".into(), "/src".into(), "".into()));
+
+        assert_eq!(
+            format_diff(&old.diff(&new)),
+            "+ /src  Src\n\
+             - /tmp/tabs  Tabs\n\
+             ~ /home/user\n    \
+             name: \"Home\" -> \"House\"\n    \
+             tags: \"me,dotfiles\" -> \"me,dotfiles,new\"\n"
+        );
+        assert_eq!(format_diff(&old.diff(&old)), "");
+    }
+}
@@ -1,5 +1,18 @@
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use std::hash::Hash;
+use serde_json::{Map, Value};
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    hash::Hash,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    fuzzy::{fuzzy_match, literal_match},
+    migrations::{migrate, FormatError, CATALOG_VERSION},
+    query::{Field, Query},
+};
 
 /* Implementing Custom Deserializer is painful so let's do this this the hacky way */
 #[derive(Debug, Serialize, Deserialize)]
@@ -7,30 +20,75 @@ struct _PathItem {
     pub name: String,
     pub full_path: String,
     pub description: String,
+    #[serde(default)]
+    pub visits: u32,
+    #[serde(default)]
+    pub last_visited: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Fields added by newer versions are kept as they are
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 struct _PathItems {
+    pub version: u64,
     pub paths: Vec<_PathItem>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 /* End of private helper structs */
 
+/// Seconds in an hour, used for aging visits
+const HOUR: u64 = 60 * 60;
+/// When total visit count of all the paths reaches this, older visits start to be forgotten
+const MAX_TOTAL_VISITS: u32 = 1000;
+/// How much frecency affects the order of search results
+const FRECENCY_WEIGHT: f64 = 10.0;
+/// Description matches are scored lower than name and path matches, since
+/// descriptions are longer and match more easily
+const DESCRIPTION_WEIGHT_DIVISOR: i64 = 2;
+
+/// Current time in seconds since unix epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
 // TODO: remove Clone!
-#[derive(Debug, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct PathItem {
     pub name: String,
     /// Lowercase name
+    #[serde(skip)]
     lname: String,
     pub full_path: String,
     /// Lowercase full_path
+    #[serde(skip)]
     lfull_path: String,
     pub description: String,
+    /// Lowercase description
+    #[serde(skip)]
+    ldescription: String,
+    /// How many times the path has been jumped into
+    pub visits: u32,
+    /// Unix time of the latest visit, 0 if never visited
+    pub last_visited: u64,
+    /// Lowercase tags for grouping paths
+    pub tags: Vec<String>,
+    /// Unknown fields from the catalog file, saved back as they are
+    #[serde(skip)]
+    extra: Map<String, Value>,
 }
 
 impl PathItem {
     pub fn new(name: String, full_path: String, description: String) -> Self {
         let lname = name.to_lowercase();
         let lfull_path = full_path.to_lowercase();
+        let ldescription = description.to_lowercase();
 
         Self {
             name,
@@ -38,8 +96,93 @@ impl PathItem {
             full_path,
             lfull_path,
             description,
+            ldescription,
+            visits: 0,
+            last_visited: 0,
+            tags: Vec::new(),
+            extra: Map::new(),
+        }
+    }
+
+    /// Turn user given tag into the saved form, `#Rust` is saved as `rust`
+    pub fn normalize_tag(tag: &str) -> String {
+        tag.trim().trim_start_matches('#').to_lowercase()
+    }
+
+    /// Add a tag. Returns false if the path already had the tag or the tag is empty
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = Self::normalize_tag(tag);
+        if tag.is_empty() || self.has_tag(&tag) {
+            return false;
+        }
+        self.tags.push(tag);
+        true
+    }
+
+    /// Remove a tag. Returns false if the path didn't have the tag
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let tag = Self::normalize_tag(tag);
+        let len = self.tags.len();
+        self.tags.retain(|t| *t != tag);
+        len != self.tags.len()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = Self::normalize_tag(tag);
+        self.tags.contains(&tag)
+    }
+
+    /// Zoxide style frecency: visit count weighted by how recent the latest visit was
+    pub fn frecency(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_visited);
+        let visits = self.visits as f64;
+        if age < HOUR {
+            visits * 4.0
+        } else if age < 24 * HOUR {
+            visits * 2.0
+        } else if age < 7 * 24 * HOUR {
+            visits / 2.0
+        } else {
+            visits / 4.0
         }
     }
+
+    /// Search score bonus of frecency
+    fn frecency_bonus(&self, now: u64) -> i64 {
+        ((self.frecency(now) + 1.0).ln() * FRECENCY_WEIGHT) as i64
+    }
+}
+
+impl PathItem {
+    /// True if the values the user gives are the same. Usage statistics aren't compared
+    pub fn same_details(&self, other: &PathItem) -> bool {
+        self.name == other.name && self.description == other.description && self.tags == other.tags
+    }
+
+    /// Carry the usage statistics and unknown fields of `saved` over when this item
+    /// replaces it
+    pub fn keep_saved_data(&mut self, saved: &PathItem) {
+        self.visits = saved.visits;
+        self.last_visited = saved.last_visited;
+        for (key, value) in &saved.extra {
+            self.extra.entry(key).or_insert_with(|| value.clone());
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.lname = name.to_lowercase();
+        self.name = name;
+    }
+
+    pub fn set_full_path(&mut self, full_path: String) {
+        self.lfull_path = full_path.to_lowercase();
+        self.full_path = full_path;
+    }
+
+    pub fn set_description(&mut self, description: String) {
+        self.ldescription = description.to_lowercase();
+        self.description = description;
+    }
 }
 
 impl PartialEq for PathItem {
@@ -57,9 +200,226 @@ impl Hash for PathItem {
     }
 }
 
+impl PathItem {
+    /// Text of `field` in the case it's searched in
+    fn field_text(&self, field: Field, case_sensitive: bool) -> Cow<'_, str> {
+        match (field, case_sensitive) {
+            (Field::Name, false) => Cow::Borrowed(&self.lname),
+            (Field::Path, false) => Cow::Borrowed(&self.lfull_path),
+            (Field::Description, false) => Cow::Borrowed(&self.ldescription),
+            (Field::Name, true) => Cow::Borrowed(&self.name),
+            (Field::Path, true) => Cow::Borrowed(&self.full_path),
+            (Field::Description, true) => Cow::Borrowed(&self.description),
+            (Field::Any | Field::Tag, _) => Cow::Borrowed(""),
+        }
+    }
+
+    /// Match the item against every term of the `query`
+    fn match_query(
+        &self,
+        query: &Query,
+        options: SearchOptions,
+        now: u64,
+    ) -> Option<PathMatch<'_>> {
+        let mut found = PathMatch::new(self);
+        if options.mode == SearchMode::Fuzzy {
+            found.score = self.frecency_bonus(now);
+        }
+
+        for term in &query.terms {
+            let fields: &[Field] = match term.field {
+                Field::Tag => {
+                    if self.has_tag(&term.text) == term.negated {
+                        return None;
+                    }
+                    continue;
+                }
+                Field::Any if options.descriptions => {
+                    &[Field::Name, Field::Path, Field::Description]
+                }
+                Field::Any => &[Field::Name, Field::Path],
+                Field::Name => &[Field::Name],
+                Field::Path => &[Field::Path],
+                Field::Description => &[Field::Description],
+            };
+
+            let text = if query.case_sensitive {
+                Cow::Borrowed(term.text.as_str())
+            } else {
+                Cow::Owned(term.text.to_lowercase())
+            };
+            // Negated terms are matched literally, fuzzy negation would exclude way too much
+            let fuzzy = options.mode == SearchMode::Fuzzy && !term.is_literal() && !term.negated;
+
+            let mut score = None;
+            let mut name_positions = None;
+            let mut path_positions = None;
+            let mut description_positions = None;
+            for field in fields {
+                let field_text = self.field_text(*field, query.case_sensitive);
+                let field_match = if fuzzy {
+                    fuzzy_match(&text, &field_text, *field == Field::Path)
+                } else {
+                    literal_match(&text, &field_text, term.anchor_start, term.anchor_end)
+                };
+
+                if let Some(field_match) = field_match {
+                    let field_score = if *field == Field::Description && term.field == Field::Any {
+                        field_match.score / DESCRIPTION_WEIGHT_DIVISOR
+                    } else {
+                        field_match.score
+                    };
+                    score = score.max(Some(field_score));
+                    match field {
+                        Field::Name => name_positions = Some(field_match.positions),
+                        Field::Path => path_positions = Some(field_match.positions),
+                        Field::Description => description_positions = Some(field_match.positions),
+                        Field::Any | Field::Tag => {}
+                    }
+                }
+            }
+
+            match (score, term.negated) {
+                (Some(_), true) | (None, false) => return None,
+                (None, true) => {}
+                (Some(score), false) => {
+                    found.score += score;
+                    found.add_positions(name_positions, path_positions, description_positions);
+                }
+            }
+        }
+
+        Some(found)
+    }
+}
+
+/// How searches are matched against the saved paths
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Characters of the words in order, best matches first
+    #[default]
+    Fuzzy,
+    /// Words as they are, in saved order
+    Substring,
+}
+
+/// Order of the PathItems before they are searched
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Path,
+    Name,
+    /// Most used paths first
+    Frecency,
+}
+
+/// Options of how PathItems are searched
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    pub mode: SearchMode,
+    /// Search words match descriptions too, not only names and paths
+    pub descriptions: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::default(),
+            descriptions: true,
+        }
+    }
+}
+
+/// PathItem that matched a search
+#[derive(Debug, Clone)]
+pub struct PathMatch<'a> {
+    pub item: &'a PathItem,
+    /// How well the item matched. Bigger is better
+    pub score: i64,
+    /// Sorted char indices of the matched characters in name
+    pub name_positions: Vec<usize>,
+    /// Sorted char indices of the matched characters in full_path
+    pub path_positions: Vec<usize>,
+    /// Sorted char indices of the matched characters in description
+    pub description_positions: Vec<usize>,
+}
+
+impl<'a> PathMatch<'a> {
+    fn new(item: &'a PathItem) -> Self {
+        Self {
+            item,
+            score: 0,
+            name_positions: Vec::new(),
+            path_positions: Vec::new(),
+            description_positions: Vec::new(),
+        }
+    }
+
+    /// Add matched positions of a search word
+    fn add_positions(
+        &mut self,
+        name: Option<Vec<usize>>,
+        full_path: Option<Vec<usize>>,
+        description: Option<Vec<usize>>,
+    ) {
+        for (positions, new) in [
+            (&mut self.name_positions, name),
+            (&mut self.path_positions, full_path),
+            (&mut self.description_positions, description),
+        ] {
+            positions.extend(new.unwrap_or_default());
+            positions.sort_unstable();
+            positions.dedup();
+        }
+    }
+}
+
+/// Differences between two versions of PathItems, see [`PathItems::diff`]
 #[derive(Debug, Default)]
+pub struct PathsDiff<'a> {
+    pub added: Vec<&'a PathItem>,
+    pub removed: Vec<&'a PathItem>,
+    /// Old and new version of the paths whose details changed
+    pub changed: Vec<(&'a PathItem, &'a PathItem)>,
+}
+
+impl PathsDiff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct PathItems {
     pub paths: Vec<PathItem>,
+    /// Unknown fields from the catalog file, saved back as they are
+    extra: Map<String, Value>,
+}
+
+/// Deserialize a migrated catalog. Migrated JSON doesn't have lines or columns, so
+/// errors name the path entry that is invalid instead
+fn from_migrated(catalog: &Value) -> serde_json::Result<_PathItems> {
+    _PathItems::deserialize(catalog).map_err(|error| {
+        let paths = catalog.get("paths").and_then(Value::as_array);
+        let invalid = paths
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .find(|(_, path)| _PathItem::deserialize(*path).is_err());
+        match invalid {
+            Some((i, path)) => {
+                let full_path = path.get("full_path").and_then(Value::as_str);
+                let entry = match full_path {
+                    Some(full_path) => format!("path {} ('{full_path}')", i + 1),
+                    None => format!("path {}", i + 1),
+                };
+                serde::de::Error::custom(format!("{error} in {entry}"))
+            }
+            None => error,
+        }
+    })
 }
 
 impl PathItems {
@@ -72,29 +432,75 @@ impl PathItems {
                 name: p.name,
                 full_path: p.full_path,
                 description: p.description,
+                visits: p.visits,
+                last_visited: p.last_visited,
+                tags: p.tags,
+                extra: p.extra,
             })
             .collect();
-        serde_json::to_string_pretty(&_PathItems { paths }).unwrap()
+        serde_json::to_string_pretty(&_PathItems {
+            version: CATALOG_VERSION,
+            paths,
+            extra: self.extra,
+        })
+        .unwrap()
     }
-    /// Create PathItems from json string
-    pub fn from_json(json: &str) -> Self {
-        let items: _PathItems = serde_json::from_str(json).unwrap_or_default();
+    /// Create PathItems from json string, upgrading older formats.
+    /// Returns the version the json was in. Empty string is an empty catalog
+    pub fn from_json(json: &str) -> Result<(Self, u64), FormatError> {
+        if json.trim().is_empty() {
+            return Ok((Self::default(), CATALOG_VERSION));
+        }
+
+        let mut value: Value = serde_json::from_str(json).map_err(FormatError::Syntax)?;
+        let version = migrate(&mut value)?;
+        // Errors from the original text tell the line and column
+        let items: _PathItems = if version == CATALOG_VERSION {
+            serde_json::from_str(json)
+        } else {
+            from_migrated(&value)
+        }
+        .map_err(FormatError::Syntax)?;
         let paths: Vec<PathItem> = items
             .paths
             .into_iter()
             .map(|p| PathItem {
                 lname: p.name.to_lowercase(),
                 lfull_path: p.full_path.to_lowercase(),
+                ldescription: p.description.to_lowercase(),
                 name: p.name,
                 full_path: p.full_path,
                 description: p.description,
+                visits: p.visits,
+                last_visited: p.last_visited,
+                tags: p.tags,
+                extra: p.extra,
             })
             .collect();
-        Self { paths }
+        Ok((
+            Self {
+                paths,
+                extra: items.extra,
+            },
+            version,
+        ))
     }
 
-    pub fn sort(&mut self) {
-        self.paths.sort_by(|a, b| a.full_path.cmp(&b.full_path))
+    pub fn sort(&mut self, order: SortOrder) {
+        match order {
+            SortOrder::Path => self.paths.sort_by(|a, b| a.full_path.cmp(&b.full_path)),
+            SortOrder::Name => self
+                .paths
+                .sort_by(|a, b| (&a.lname, &a.full_path).cmp(&(&b.lname, &b.full_path))),
+            SortOrder::Frecency => {
+                let now = unix_time();
+                self.paths.sort_by(|a, b| {
+                    b.frecency(now)
+                        .total_cmp(&a.frecency(now))
+                        .then_with(|| a.full_path.cmp(&b.full_path))
+                })
+            }
+        }
     }
 
     /// Add new path, overriding old entries based oh full_path
@@ -106,37 +512,148 @@ impl PathItems {
         self.paths.push(path);
     }
 
+    /// Apply the changes made between `base` and `edited` on top of self.
+    /// Lets an edit based on an older copy of the catalog keep the changes
+    /// that were saved meanwhile. Returns false if nothing changed
+    pub fn merge_changes(&mut self, base: &PathItems, edited: &PathItems) -> bool {
+        let mut changed = false;
+        for old in &base.paths {
+            if !edited.exists(&old.full_path) {
+                changed |= self.remove(&old.full_path);
+            }
+        }
+
+        for new in &edited.paths {
+            let unchanged = base
+                .get(&new.full_path)
+                .is_some_and(|old| old.same_details(new));
+            if unchanged {
+                continue;
+            }
+
+            changed = true;
+            match self.get_mut(&new.full_path) {
+                // Visits might have been recorded meanwhile, so only the editable values are merged
+                Some(current) => {
+                    current.set_name(new.name.clone());
+                    current.set_description(new.description.clone());
+                    current.tags = new.tags.clone();
+                }
+                None => self.paths.push(new.clone()),
+            }
+        }
+
+        changed
+    }
+
+    /// What was added, removed and changed to get from self to `other`.
+    /// Only the values the user gives are compared, visits are ignored
+    pub fn diff<'a>(&'a self, other: &'a PathItems) -> PathsDiff<'a> {
+        let mut diff = PathsDiff::default();
+        for old in &self.paths {
+            match other.get(&old.full_path) {
+                Some(new) if !old.same_details(new) => diff.changed.push((old, new)),
+                Some(_) => {}
+                None => diff.removed.push(old),
+            }
+        }
+        diff.added = other
+            .paths
+            .iter()
+            .filter(|new| !self.exists(&new.full_path))
+            .collect();
+
+        diff
+    }
+
+    /// Record a visit to the path. Returns false if the path isn't saved
+    pub fn visit(&mut self, path: &str) -> bool {
+        let now = unix_time();
+        let item = match self.get_mut(path) {
+            Some(item) => item,
+            None => return false,
+        };
+        item.visits = item.visits.saturating_add(1);
+        item.last_visited = now;
+
+        // Age all the visits so old favourites make room for the new ones.
+        // Paths are kept even if their visits age to zero since they are saved by the user
+        let total: u32 = self
+            .paths
+            .iter()
+            .map(|p| p.visits)
+            .fold(0, u32::saturating_add);
+        if total > MAX_TOTAL_VISITS {
+            for item in &mut self.paths {
+                item.visits = item.visits * 9 / 10;
+            }
+        }
+        true
+    }
+
     /// Check if there's a PathItem that has the same path
     pub fn exists(&self, path: &str) -> bool {
         self.paths.iter().any(|p| p.full_path == path)
     }
 
+    /// Get mutable PathItem that has the same path
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut PathItem> {
+        self.paths.iter_mut().find(|p| p.full_path == path)
+    }
+
+    /// Remove the PathItem that has the same path. Returns false if there was nothing to remove
+    pub fn remove(&mut self, path: &str) -> bool {
+        let len = self.paths.len();
+        self.paths.retain(|p| p.full_path != path);
+        len != self.paths.len()
+    }
+
+    /// Find PathItems with case insensitively matching name
+    pub fn find_by_name(&self, name: &str) -> Vec<&PathItem> {
+        let name = name.to_lowercase();
+        self.paths.iter().filter(|p| p.lname == name).collect()
+    }
+
+    /// Get the PathItem that has the same path
+    pub fn get(&self, path: &str) -> Option<&PathItem> {
+        self.paths.iter().find(|p| p.full_path == path)
+    }
+
     /// Find Path items that match the search
-    /// search will be OK if all words in [search] are part of PathItem::name or PathItem::full_path
-    pub fn filter<'a>(&'a self, search: &str) -> Vec<&'a PathItem> {
-        let words: Vec<&str> = search.split_whitespace().collect();
-        // Search is empty or only contains whitespace
-        if words.is_empty() {
-            return self.paths.iter().collect();
-        }
+    /// search will be OK if all words in [search] are part of PathItem::name, PathItem::full_path
+    /// or, if [`SearchOptions::descriptions`] is set, PathItem::description.
+    /// In fuzzy mode the results are ordered from the best match to the worst
+    pub fn filter<'a>(&'a self, search: &str, options: SearchOptions) -> Vec<&'a PathItem> {
+        self.search(search, options)
+            .into_iter()
+            .map(|found| found.item)
+            .collect()
+    }
 
-        self.paths
+    /// Like [`PathItems::filter`] but also tells which characters matched the search.
+    /// See [`crate::query`] for the search syntax
+    pub fn search<'a>(&'a self, search: &str, options: SearchOptions) -> Vec<PathMatch<'a>> {
+        let query = Query::parse(search);
+        let now = unix_time();
+        let mut matches: Vec<PathMatch> = self
+            .paths
             .iter()
-            .filter(move |&path| {
-                for word in &words {
-                    if !path.lfull_path.contains(word) && !path.lname.contains(word) {
-                        return false;
-                    }
-                }
-                true
-            })
-            .collect()
+            .filter_map(|path| path.match_query(&query, options, now))
+            .collect();
+
+        if options.mode == SearchMode::Fuzzy {
+            // Stable sort keeps the saved order for equally good matches
+            matches.sort_by_key(|found| Reverse(found.score));
+        }
+        matches
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_conversion)]
 mod tests {
-    use super::{PathItem, PathItems};
+    use super::{PathItem, PathItems, SearchMode, SearchOptions, SortOrder};
+    use crate::migrations::{FormatError, CATALOG_VERSION};
 
     #[test]
     fn test_basic_find() {
@@ -148,6 +665,7 @@ mod tests {
                     full_path: "/home/path".into(),
                     lfull_path: "/home/path".into(),
                     description: "The path user's home folder".into(),
+                    ..Default::default()
                 },
                 PathItem {
                     name: "Secret Way!".into(),
@@ -155,17 +673,19 @@ mod tests {
                     full_path: "/root/path".into(),
                     lfull_path: "/root/path".into(),
                     description: "Secret path for a root user".into(),
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
-        assert_eq!(items.filter("").len(), 2);
-        assert_eq!(items.filter("   ").len(), 2);
-        assert_eq!(items.filter("\t  ").len(), 2);
-        assert_eq!(items.filter("path").len(), 2);
-        assert_eq!(items.filter("name").len(), 1);
-        assert_eq!(items.filter("root").len(), 1);
-        assert_eq!(items.filter("foobar").len(), 0);
+        assert_eq!(items.filter("", SearchOptions::default()).len(), 2);
+        assert_eq!(items.filter("   ", SearchOptions::default()).len(), 2);
+        assert_eq!(items.filter("\t  ", SearchOptions::default()).len(), 2);
+        assert_eq!(items.filter("path", SearchOptions::default()).len(), 2);
+        assert_eq!(items.filter("name", SearchOptions::default()).len(), 1);
+        assert_eq!(items.filter("root", SearchOptions::default()).len(), 1);
+        assert_eq!(items.filter("foobar", SearchOptions::default()).len(), 0);
     }
 
     #[test]
@@ -178,6 +698,7 @@ mod tests {
                     full_path: "/home/path/user".into(),
                     lfull_path: "/home/path/user".into(),
                     description: "The path user's home folder".into(),
+                    ..Default::default()
                 },
                 PathItem {
                     name: "Secret Way Word!".into(),
@@ -185,15 +706,381 @@ mod tests {
                     full_path: "/root/path/".into(),
                     lfull_path: "/root/path/".into(),
                     description: "Secret path for a root user".into(),
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
+        };
+
+        assert_eq!(items.filter("home word", SearchOptions::default()).len(), 1);
+        assert_eq!(items.filter("home user", SearchOptions::default()).len(), 1);
+        assert_eq!(
+            items.filter("secret word", SearchOptions::default()).len(),
+            1
+        );
+        assert_eq!(
+            items
+                .filter("secret       word", SearchOptions::default())
+                .len(),
+            1
+        );
+        assert_eq!(
+            items
+                .filter("secret home word", SearchOptions::default())
+                .len(),
+            0
+        );
+        assert_eq!(items.filter("root user", SearchOptions::default()).len(), 0);
+    }
+
+    #[test]
+    fn test_fuzzy_find() {
+        let items = PathItems {
+            paths: vec![
+                PathItem::new("Manager".into(), "/home/user/mgr".into(), "".into()),
+                PathItem::new("Path".into(), "/home/user/path-manager".into(), "".into()),
+                PathItem::new("Other".into(), "/srv/other".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+        let substring = SearchOptions {
+            mode: SearchMode::Substring,
+            ..Default::default()
+        };
+
+        let found = items.filter("pmgr", SearchOptions::default());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].full_path, "/home/user/path-manager");
+        assert_eq!(items.filter("pmgr", substring).len(), 0);
+
+        // Best match comes first in fuzzy mode and in saved order in substring mode
+        let found = items.filter("mgr", SearchOptions::default());
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].name, "Manager");
+        let found = items.filter("path-manager", SearchOptions::default());
+        assert_eq!(found[0].name, "Path");
+        let found = items.filter("user", substring);
+        assert_eq!(found[0].name, "Manager");
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn test_match_positions() {
+        let items = PathItems {
+            paths: vec![PathItem::new(
+                "Path Manager".into(),
+                "/src/path-manager".into(),
+                "".into(),
+            )],
+            ..Default::default()
+        };
+        let substring = SearchOptions {
+            mode: SearchMode::Substring,
+            ..Default::default()
+        };
+
+        let found = items.search("pmgr", SearchOptions::default());
+        assert_eq!(found[0].path_positions, vec![5, 10, 14, 16]);
+        assert_eq!(found[0].name_positions, vec![0, 5, 9, 11]);
+
+        let found = items.search("src man", substring);
+        assert_eq!(found[0].path_positions, vec![1, 2, 3, 10, 11, 12]);
+        assert_eq!(found[0].name_positions, vec![5, 6, 7]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut items = PathItems {
+            paths: vec![
+                PathItem::new("Home".into(), "/home/user".into(), "".into()),
+                PathItem::new("home".into(), "/root".into(), "".into()),
+                PathItem::new("Tmp".into(), "/tmp".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(items.find_by_name("HOME").len(), 2);
+        assert!(items.remove("/root"));
+        assert!(!items.remove("/root"));
+        assert_eq!(items.find_by_name("home").len(), 1);
+        assert_eq!(items.paths.len(), 2);
+    }
+
+    #[test]
+    fn test_frecency() {
+        let mut items = PathItems {
+            paths: vec![
+                PathItem::new("Project".into(), "/src/project".into(), "".into()),
+                PathItem::new("Project old".into(), "/src/project-old".into(), "".into()),
+                PathItem::new("Other".into(), "/src/other".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+
+        assert!(items.visit("/src/project-old"));
+        assert!(items.visit("/src/project-old"));
+        assert!(!items.visit("/not/saved"));
+        assert_eq!(items.paths[1].visits, 2);
+
+        let found = items.filter("", SearchOptions::default());
+        assert_eq!(found[0].full_path, "/src/project-old");
+        let found = items.filter("project", SearchOptions::default());
+        assert_eq!(found[0].full_path, "/src/project-old");
+
+        // Visits that happened long ago matter less
+        let now = items.paths[1].last_visited;
+        items.paths[1].last_visited = now - 30 * 24 * super::HOUR;
+        assert!(items.paths[1].frecency(now) < 2.0);
+    }
+
+    #[test]
+    fn test_visit_aging() {
+        let mut items = PathItems {
+            paths: vec![
+                PathItem::new("A".into(), "/a".into(), "".into()),
+                PathItem::new("B".into(), "/b".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+        items.paths[0].visits = super::MAX_TOTAL_VISITS;
+        items.paths[1].visits = 5;
+
+        items.visit("/b");
+        assert_eq!(items.paths[0].visits, super::MAX_TOTAL_VISITS * 9 / 10);
+        assert_eq!(items.paths[1].visits, 5);
+        assert_eq!(items.paths.len(), 2);
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut items = PathItems {
+            paths: vec![
+                PathItem::new("Api".into(), "/work/api".into(), "".into()),
+                PathItem::new("Web".into(), "/work/web".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+        assert!(items.paths[0].add_tag("#Rust"));
+        assert!(!items.paths[0].add_tag("rust"));
+        assert!(items.paths[0].add_tag("client-a"));
+        assert!(items.paths[1].add_tag("client-a"));
+        assert_eq!(items.paths[0].tags, vec!["rust", "client-a"]);
+
+        let options = SearchOptions::default();
+        assert_eq!(items.filter("#rust", options).len(), 1);
+        assert_eq!(items.filter("tag:Client-A", options).len(), 2);
+        assert_eq!(items.filter("#client-a web", options).len(), 1);
+        assert_eq!(items.filter("#client-a #rust web", options).len(), 0);
+        assert_eq!(items.filter("#go", options).len(), 0);
+
+        assert!(items.paths[0].remove_tag("RUST"));
+        assert!(!items.paths[0].remove_tag("rust"));
+        assert_eq!(items.filter("#rust", options).len(), 0);
+    }
+
+    #[test]
+    fn test_query_syntax() {
+        let mut items = PathItems {
+            paths: vec![
+                PathItem::new(
+                    "Billing".into(),
+                    "/work/billing".into(),
+                    "Billing API".into(),
+                ),
+                PathItem::new("Api".into(), "/work/api-old".into(), "Old API".into()),
+                PathItem::new("Web".into(), "/home/web".into(), "Website".into()),
+            ],
+            ..Default::default()
+        };
+        items.paths[2].add_tag("frontend");
+        let options = SearchOptions::default();
+        let substring = SearchOptions {
+            mode: SearchMode::Substring,
+            ..Default::default()
+        };
+        let paths = |search, options| -> Vec<String> {
+            items
+                .filter(search, options)
+                .iter()
+                .map(|p| p.full_path.clone())
+                .collect()
+        };
+
+        assert_eq!(paths("name:api", options), vec!["/work/api-old"]);
+        assert_eq!(paths("path:web", options), vec!["/home/web"]);
+        assert_eq!(
+            paths("desc:\"billing api\"", options),
+            vec!["/work/billing"]
+        );
+        assert_eq!(paths("^/work -old", substring), vec!["/work/billing"]);
+        assert_eq!(paths("-#frontend", substring).len(), 2);
+        assert_eq!(paths("old$", substring), vec!["/work/api-old"]);
+        assert_eq!(paths("^/home/web$", options), vec!["/home/web"]);
+        assert_eq!(paths("\"bil ing\"", options).len(), 0);
+        // Negation is literal even in fuzzy mode
+        assert_eq!(paths("-bln", options).len(), 3);
+
+        // Smart case
+        assert_eq!(paths("billing", substring).len(), 1);
+        assert_eq!(paths("Billing", substring).len(), 1);
+        assert_eq!(paths("BILLING", substring).len(), 0);
+        assert_eq!(paths("desc:API", substring).len(), 2);
+        assert_eq!(paths("desc:Api", substring).len(), 0);
+    }
+
+    #[test]
+    fn test_description_search() {
+        let items = PathItems {
+            paths: vec![
+                PathItem::new("Pay".into(), "/work/pay".into(), "Billing API".into()),
+                PathItem::new("Billing".into(), "/work/billing".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+        let options = SearchOptions::default();
+        let no_descriptions = SearchOptions {
+            descriptions: false,
+            ..Default::default()
+        };
+
+        let found = items.search("billing", options);
+        assert_eq!(found.len(), 2);
+        // Name and path matches rank higher than description matches
+        assert_eq!(found[0].item.name, "Billing");
+        assert_eq!(found[1].description_positions, vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(items.filter("billing api", options).len(), 1);
+        assert_eq!(items.filter("billing", no_descriptions).len(), 1);
+        // Qualifier searches the description even if descriptions aren't searched by default
+        assert_eq!(items.filter("desc:api", no_descriptions).len(), 1);
+    }
+
+    #[test]
+    fn test_merge_changes() {
+        let base = PathItems {
+            paths: vec![
+                PathItem::new("a".into(), "/a".into(), "".into()),
+                PathItem::new("b".into(), "/b".into(), "".into()),
+                PathItem::new("c".into(), "/c".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+        // Edit removed /a, renamed /b and added /d
+        let edited = PathItems {
+            paths: vec![
+                PathItem::new("renamed".into(), "/b".into(), "".into()),
+                PathItem::new("c".into(), "/c".into(), "".into()),
+                PathItem::new("d".into(), "/d".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+        // Meanwhile /b was visited and /e added
+        let mut current = PathItems {
+            paths: vec![
+                PathItem::new("a".into(), "/a".into(), "".into()),
+                PathItem::new("b".into(), "/b".into(), "".into()),
+                PathItem::new("c".into(), "/c".into(), "".into()),
+                PathItem::new("e".into(), "/e".into(), "".into()),
+            ],
+            ..Default::default()
+        };
+        current.visit("/b");
+
+        assert!(current.merge_changes(&base, &edited));
+        current.sort(SortOrder::Path);
+        let paths: Vec<&str> = current.paths.iter().map(|p| p.full_path.as_str()).collect();
+        assert_eq!(paths, vec!["/b", "/c", "/d", "/e"]);
+        let b = current.get("/b").unwrap();
+        assert_eq!(b.name, "renamed");
+        assert_eq!(b.visits, 1);
+
+        assert!(!current.merge_changes(&edited, &edited));
+    }
+
+    #[test]
+    fn test_from_json() {
+        assert!(PathItems::from_json("").unwrap().0.paths.is_empty());
+        assert!(PathItems::from_json(" \n").unwrap().0.paths.is_empty());
+
+        let (items, version) = PathItems::from_json(
+            r#"{"paths": [{"name": "a", "full_path": "/a", "description": "A"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(items.paths.len(), 1);
+        assert_eq!(items.filter("a", SearchOptions::default()).len(), 1);
+
+        match PathItems::from_json("{\n  \"paths\": [\n    {\"name\": }\n") {
+            Err(FormatError::Syntax(error)) => assert_eq!((error.line(), error.column()), (3, 14)),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_from_json_type_error() {
+        let current = r#"{"version": 2, "paths": [{"name": "a", "full_path": "/a", "description": ""},
+            {"name": 1, "full_path": "/b", "description": ""}]}"#;
+        match PathItems::from_json(current) {
+            Err(FormatError::Syntax(error)) => {
+                assert!(error.to_string().contains("expected a string"));
+                assert_eq!((error.line(), error.column()), (2, 22));
+            }
+            other => panic!("Unexpected result: {other:?}"),
+        }
+
+        let old = r#"{"paths": [{"name": "a", "full_path": "/a", "description": ""},
+            {"name": 1, "full_path": "/b", "description": ""}]}"#;
+        match PathItems::from_json(old) {
+            Err(FormatError::Syntax(error)) => {
+                assert!(error.to_string().contains("in path 2 ('/b')"), "{error}")
+            }
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let json = r#"{"version": 2, "synced": true, "paths": [{"name": "a",
+            "full_path": "/a", "description": "", "color": "red"}]}"#;
+        let (mut items, version) = PathItems::from_json(json).unwrap();
+        assert_eq!(version, CATALOG_VERSION);
+        items.get_mut("/a").unwrap().set_name("b".into());
+
+        let saved: serde_json::Value = serde_json::from_str(&items.into_json()).unwrap();
+        assert_eq!(saved["synced"], true);
+        assert_eq!(saved["paths"][0]["color"], "red");
+        assert_eq!(saved["paths"][0]["name"], "b");
+
+        // Replacing the entry keeps its unknown fields
+        let (mut items, _) = PathItems::from_json(json).unwrap();
+        let mut replacement = PathItem::new("c".into(), "/a".into(), "".into());
+        replacement.keep_saved_data(items.get("/a").unwrap());
+        items.add_path(replacement);
+        let saved: serde_json::Value = serde_json::from_str(&items.into_json()).unwrap();
+        assert_eq!(saved["paths"][0]["color"], "red");
+        assert_eq!(saved["paths"][0]["name"], "c");
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = PathItems {
+            paths: vec![
+                PathItem::new("a".into(), "/a".into(), "".into()),
+                PathItem::new("b".into(), "/b".into(), "".into()),
+                PathItem::new("c".into(), "/c".into(), "".into()),
+            ],
+            ..Default::default()
         };
+        let mut new = old.clone();
+        new.remove("/a");
+        new.get_mut("/b").unwrap().add_tag("work");
+        new.visit("/c");
+        new.add_path(PathItem::new("d".into(), "/d".into(), "".into()));
 
-        assert_eq!(items.filter("home word").len(), 1);
-        assert_eq!(items.filter("home user").len(), 1);
-        assert_eq!(items.filter("secret word").len(), 1);
-        assert_eq!(items.filter("secret       word").len(), 1);
-        assert_eq!(items.filter("secret home word").len(), 0);
-        assert_eq!(items.filter("root user").len(), 0);
+        let diff = old.diff(&new);
+        assert_eq!(diff.removed[0].full_path, "/a");
+        assert_eq!(diff.added[0].full_path, "/d");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].1.tags, vec!["work"]);
+        assert!(new.diff(&new).is_empty());
     }
 }
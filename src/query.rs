@@ -0,0 +1,236 @@
+//! query.rs contains the parser of the search language shared by every search.
+//!
+//! Words are separated by whitespace and every word has to match. Supported syntax:
+//! - `name:word`, `path:word`, `desc:word` only match the given field
+//! - `#tag` and `tag:tag` only match paths with the tag
+//! - `-word` excludes paths that match the word
+//! - `"quoted words"` match exactly, whitespace included
+//! - `^word` matches the start and `word$` the end of a field
+//!
+//! Search is case sensitive only if it contains uppercase characters.
+
+/// Field of a PathItem a search term is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Name, path or description if descriptions are searched
+    Any,
+    Name,
+    Path,
+    Description,
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub field: Field,
+    pub text: String,
+    /// Paths matching the term are excluded
+    pub negated: bool,
+    /// Quoted term that has to match exactly, never fuzzily
+    pub exact: bool,
+    /// Term has to match the start of the field
+    pub anchor_start: bool,
+    /// Term has to match the end of the field
+    pub anchor_end: bool,
+}
+
+impl Term {
+    /// Term has to be found as it is, not fuzzily
+    pub fn is_literal(&self) -> bool {
+        self.exact || self.anchor_start || self.anchor_end
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+    /// Search contains uppercase characters
+    pub case_sensitive: bool,
+}
+
+impl Query {
+    pub fn parse(search: &str) -> Self {
+        let mut terms = Vec::new();
+        let mut chars = search.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let mut word = String::new();
+            let mut quoted = None;
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                if c == '"' {
+                    // Everything until the closing quote, or the end of the search
+                    let phrase: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                    quoted = Some(phrase);
+                    break;
+                }
+                word.push(c);
+            }
+
+            if let Some(term) = Term::parse(&word, quoted) {
+                terms.push(term);
+            }
+        }
+
+        let case_sensitive = terms
+            .iter()
+            .filter(|term| term.field != Field::Tag)
+            .any(|term| term.text.chars().any(char::is_uppercase));
+
+        Self {
+            terms,
+            case_sensitive,
+        }
+    }
+}
+
+impl Term {
+    /// Parse a single search word. `quoted` is the phrase in quotes after the `prefix`, if any
+    fn parse(prefix: &str, quoted: Option<String>) -> Option<Self> {
+        let (negated, rest) = match prefix.strip_prefix('-') {
+            // Lone `-` is just a character to search
+            Some(rest) if !rest.is_empty() || quoted.is_some() => (true, rest),
+            _ => (false, prefix),
+        };
+
+        let (field, rest) = if let Some(tag) = rest.strip_prefix('#') {
+            (Field::Tag, tag)
+        } else {
+            match rest.split_once(':') {
+                Some(("name", rest)) => (Field::Name, rest),
+                Some(("path", rest)) => (Field::Path, rest),
+                Some(("desc" | "description", rest)) => (Field::Description, rest),
+                Some(("tag", rest)) => (Field::Tag, rest),
+                _ => (Field::Any, rest),
+            }
+        };
+
+        let mut term = Term {
+            field,
+            text: String::new(),
+            negated,
+            exact: false,
+            anchor_start: false,
+            anchor_end: false,
+        };
+
+        match quoted {
+            Some(phrase) => {
+                term.exact = true;
+                term.text = format!("{rest}{phrase}");
+            }
+            None => {
+                let mut text = rest;
+                if field != Field::Tag {
+                    if let Some(stripped) = text.strip_prefix('^') {
+                        term.anchor_start = true;
+                        text = stripped;
+                    }
+                    if let Some(stripped) = text.strip_suffix('$') {
+                        term.anchor_end = true;
+                        text = stripped;
+                    }
+                }
+                term.text = text.into();
+            }
+        }
+
+        if term.text.is_empty() && !(term.anchor_start && term.anchor_end) {
+            return None;
+        }
+        Some(term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Field, Query, Term};
+
+    fn term(field: Field, text: &str) -> Term {
+        Term {
+            field,
+            text: text.into(),
+            negated: false,
+            exact: false,
+            anchor_start: false,
+            anchor_end: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let query = Query::parse("  foo name:bar path:/src desc:api #rust tag:web http://x  ");
+        assert_eq!(
+            query.terms,
+            vec![
+                term(Field::Any, "foo"),
+                term(Field::Name, "bar"),
+                term(Field::Path, "/src"),
+                term(Field::Description, "api"),
+                term(Field::Tag, "rust"),
+                term(Field::Tag, "web"),
+                term(Field::Any, "http://x"),
+            ]
+        );
+        assert!(!query.case_sensitive);
+        assert_eq!(Query::parse(" \t ").terms, vec![]);
+    }
+
+    #[test]
+    fn test_parse_modifiers() {
+        let query = Query::parse(r#"-old "billing api" desc:"two words" ^/home manager$ - ^x$"#);
+        assert_eq!(
+            query.terms,
+            vec![
+                Term {
+                    negated: true,
+                    ..term(Field::Any, "old")
+                },
+                Term {
+                    exact: true,
+                    ..term(Field::Any, "billing api")
+                },
+                Term {
+                    exact: true,
+                    ..term(Field::Description, "two words")
+                },
+                Term {
+                    anchor_start: true,
+                    ..term(Field::Any, "/home")
+                },
+                Term {
+                    anchor_end: true,
+                    ..term(Field::Any, "manager")
+                },
+                term(Field::Any, "-"),
+                Term {
+                    anchor_start: true,
+                    anchor_end: true,
+                    ..term(Field::Any, "x")
+                },
+            ]
+        );
+
+        let query = Query::parse(r#"-"unterminated phrase"#);
+        assert_eq!(
+            query.terms,
+            vec![Term {
+                negated: true,
+                exact: true,
+                ..term(Field::Any, "unterminated phrase")
+            }]
+        );
+    }
+
+    #[test]
+    fn test_smart_case() {
+        assert!(Query::parse("foo Bar").case_sensitive);
+        assert!(!Query::parse("foo bar").case_sensitive);
+        // Tags are always case insensitive
+        assert!(!Query::parse("#Rust foo").case_sensitive);
+    }
+}
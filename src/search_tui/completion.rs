@@ -0,0 +1,82 @@
+use std::{env, fs, path::Path};
+
+/// Longest common prefix of `a` and `b`
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, ac), bc)| ac != bc)
+        .map(|((i, _), _)| i)
+        .unwrap_or_else(|| a.len().min(b.len()));
+    &a[..len]
+}
+
+/// Complete `input` against directories in the filesystem like a shell would.
+/// Returns None if there's nothing to complete
+pub fn complete_path(input: &str) -> Option<String> {
+    let input = match (input.strip_prefix('~'), env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => home + rest,
+        _ => input.to_string(),
+    };
+
+    let (dir, prefix) = match input.rfind('/') {
+        Some(idx) => (&input[..=idx], &input[idx + 1..]),
+        None => ("", input.as_str()),
+    };
+    let read_dir = if dir.is_empty() { "." } else { dir };
+
+    let mut matches: Vec<String> = fs::read_dir(read_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| Path::new(read_dir).join(entry.file_name()).is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        // Hidden directories are only completed when asked for
+        .filter(|name| {
+            name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.'))
+        })
+        .collect();
+    matches.sort();
+
+    let completed = match matches.as_slice() {
+        [] => return None,
+        [only] => format!("{only}/"),
+        [first, rest @ ..] => rest
+            .iter()
+            .fold(first.as_str(), |prefix, name| common_prefix(prefix, name))
+            .to_string(),
+    };
+
+    Some(format!("{dir}{completed}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::complete_path;
+    use std::fs;
+
+    #[test]
+    fn test_complete_path() {
+        let root = std::env::temp_dir().join(format!("pm-completion-{}", std::process::id()));
+        for dir in ["project-one", "project-two", "other", ".hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("project-file"), "").unwrap();
+        let root_str = root.to_str().unwrap();
+
+        assert_eq!(
+            complete_path(&format!("{root_str}/pro")),
+            Some(format!("{root_str}/project-"))
+        );
+        assert_eq!(
+            complete_path(&format!("{root_str}/project-o")),
+            Some(format!("{root_str}/project-one/"))
+        );
+        assert_eq!(
+            complete_path(&format!("{root_str}/.h")),
+            Some(format!("{root_str}/.hidden/"))
+        );
+        assert_eq!(complete_path(&format!("{root_str}/nothing")), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
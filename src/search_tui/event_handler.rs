@@ -1,8 +1,69 @@
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use std::io;
 
-use super::tui_state::{InputMode, PathEditCommand, TuiState};
+use super::tui_state::{EditField, InputMode, PathEditCommand, TuiState};
 
+/// Byte index of the character `cursor` is at. Cursors count characters but
+/// Strings are indexed by bytes
+fn byte_index(input: &str, cursor: u16) -> usize {
+    input
+        .char_indices()
+        .nth(cursor as usize)
+        .map_or(input.len(), |(i, _)| i)
+}
+
+/// Handle line editing keys of a text input. Returns true if `input` changed
+fn edit_text(input: &mut String, cursor: &mut u16, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::End => {
+            *cursor = input.chars().count() as u16;
+        }
+        KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            *cursor = input.chars().count() as u16;
+        }
+        KeyCode::Home => {
+            *cursor = 0;
+        }
+        KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            *cursor = 0;
+        }
+        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            input.clear();
+            *cursor = 0;
+            return true;
+        }
+        KeyCode::Left => {
+            *cursor = cursor.saturating_sub(1);
+        }
+        KeyCode::Right => {
+            // as usize cast is safe since u16 always fits in usize
+            *cursor = if (*cursor as usize) < input.chars().count() {
+                *cursor + 1
+            } else {
+                *cursor
+            }
+        }
+        KeyCode::Char(c) => {
+            input.insert(byte_index(input, *cursor), c);
+            *cursor += 1;
+            return true;
+        }
+        KeyCode::Backspace => {
+            *cursor = cursor.saturating_sub(1);
+            if !input.is_empty() {
+                input.remove(byte_index(input, *cursor));
+            }
+            return true;
+        }
+        _ => {}
+    }
+
+    false
+}
+
+// Conditions of the movement keys are kept inside the arms so a key never falls
+// through to another binding
+#[allow(clippy::collapsible_match)]
 pub fn handle_event<'a>(app: &'a mut TuiState<'a>) -> io::Result<&'a mut TuiState<'a>> {
     if let Event::Key(key) = event::read()? {
         // Ctrl-C will close out the program instantly
@@ -35,15 +96,36 @@ pub fn handle_event<'a>(app: &'a mut TuiState<'a>) -> io::Result<&'a mut TuiStat
                         app.set_highlighted();
                     }
                 }
+                KeyCode::Enter if app.edit_mode => {
+                    app.start_review();
+                }
                 KeyCode::Enter => {
                     if !app.filtered.is_empty() {
                         app.selected_path = app.highlighted;
                     }
                 }
-                KeyCode::Char('d') => {
-                    if app.edit_mode {
-                        app.set_path_command(PathEditCommand::Delete);
-                    }
+                KeyCode::Char('u') if app.edit_mode => {
+                    app.undo();
+                }
+                KeyCode::Char('r')
+                    if app.edit_mode && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    app.redo();
+                }
+                KeyCode::Char('d') if app.edit_mode => {
+                    app.set_path_command(PathEditCommand::Delete);
+                }
+                KeyCode::Char('r') if app.edit_mode => {
+                    app.start_edit(EditField::Name);
+                }
+                KeyCode::Char('e') if app.edit_mode => {
+                    app.start_edit(EditField::Description);
+                }
+                KeyCode::Char('m') if app.edit_mode => {
+                    app.start_edit(EditField::Path);
+                }
+                KeyCode::Char('a') if app.edit_mode => {
+                    app.start_add();
                 }
                 _ => {}
             },
@@ -60,60 +142,128 @@ pub fn handle_event<'a>(app: &'a mut TuiState<'a>) -> io::Result<&'a mut TuiStat
                         app.set_highlighted();
                     }
                 }
-                KeyCode::End => {
-                    app.cursor = app.input.chars().count() as u16;
+                KeyCode::Esc | KeyCode::Enter => {
+                    app.input_mode = InputMode::Select;
                 }
-                KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    app.cursor = app.input.chars().count() as u16;
+                _ => {
+                    input_changed = edit_text(&mut app.input, &mut app.cursor, key);
                 }
-                KeyCode::Home => {
-                    app.cursor = 0;
+            },
+            InputMode::Edit(field) => match key.code {
+                KeyCode::Esc => {
+                    app.message = None;
+                    app.input_mode = InputMode::Select;
                 }
-                KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    app.cursor = 0;
+                KeyCode::Enter => app.finish_edit(field),
+                _ => {
+                    edit_text(&mut app.edit_input, &mut app.edit_cursor, key);
                 }
-                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    app.input.clear();
-                    app.cursor = 0;
-                    input_changed = true;
+            },
+            InputMode::Review => match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    app.confirmed = true;
                 }
-                KeyCode::Left => {
-                    app.cursor = app.cursor.saturating_sub(1);
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    app.input_mode = InputMode::Select;
                 }
-                KeyCode::Right => {
-                    // as usize cast is safe since u16 always fits in usize
-                    app.cursor = if (app.cursor as usize) < app.input.chars().count() {
-                        app.cursor + 1
-                    } else {
-                        app.cursor
-                    }
+                _ => {}
+            },
+            InputMode::Add => match key.code {
+                KeyCode::Esc => {
+                    app.message = None;
+                    app.input_mode = InputMode::Select;
                 }
-                KeyCode::Char(c) => {
-                    // as usize cast is safe since u16 always fits in usize
-                    app.input.insert(app.cursor as usize, c);
-                    app.cursor += 1;
-                    input_changed = true;
-                }
-                KeyCode::Backspace => {
-                    app.cursor = app.cursor.saturating_sub(1);
-                    if !app.input.is_empty() {
-                        // as usize cast is safe since u16 always fits in usize
-                        app.input.remove(app.cursor as usize);
-                    }
-                    input_changed = true;
+                KeyCode::Tab if app.add_form.field == EditField::Path => {
+                    app.add_form.complete_path();
                 }
-                KeyCode::Esc | KeyCode::Enter => {
-                    app.input_mode = InputMode::Select;
+                KeyCode::Tab | KeyCode::Down => app.add_form.move_focus(1),
+                KeyCode::BackTab | KeyCode::Up => app.add_form.move_focus(-1),
+                KeyCode::Enter if app.add_form.is_last_field() => app.finish_add(),
+                KeyCode::Enter => app.add_form.move_focus(1),
+                _ => {
+                    let (input, cursor) = app.add_form.input_mut();
+                    edit_text(input, cursor, key);
                 }
-                _ => {}
             },
         }
 
         if input_changed {
-            app.filtered = app.items.filter(&app.input);
+            app.filtered = app.items.search(&app.input, app.search);
             app.set_highlighted();
         }
     }
 
     Ok(app)
 }
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::edit_text;
+    use crate::{
+        paths::{PathItem, PathItems, SearchOptions},
+        search_tui::tui_state::{AddForm, EditField, TuiState},
+        settings::StartMode,
+    };
+
+    fn type_keys(input: &mut String, cursor: &mut u16, keys: &[KeyCode]) {
+        for key in keys {
+            edit_text(input, cursor, KeyEvent::new(*key, KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn test_edit_non_ascii_name() {
+        let mut items = PathItems::default();
+        items.paths = vec![PathItem::new("Café".into(), "/cafe".into(), "日本".into())];
+        let mut state = TuiState::new(
+            &items,
+            &[],
+            true,
+            SearchOptions::default(),
+            StartMode::Select,
+        );
+
+        state.start_edit(EditField::Name);
+        type_keys(
+            &mut state.edit_input,
+            &mut state.edit_cursor,
+            &[KeyCode::Char('s'), KeyCode::Left, KeyCode::Backspace],
+        );
+        assert_eq!(state.edit_input, "Cafs");
+
+        state.start_edit(EditField::Description);
+        type_keys(
+            &mut state.edit_input,
+            &mut state.edit_cursor,
+            &[
+                KeyCode::Left,
+                KeyCode::Char('の'),
+                KeyCode::End,
+                KeyCode::Backspace,
+            ],
+        );
+        assert_eq!(state.edit_input, "日の");
+    }
+
+    #[test]
+    fn test_add_form_non_ascii() {
+        let mut form = AddForm::default();
+        form.move_focus(1);
+        let (input, cursor) = form.input_mut();
+        type_keys(
+            input,
+            cursor,
+            &[
+                KeyCode::Char('日'),
+                KeyCode::Char('本'),
+                KeyCode::Left,
+                KeyCode::Char('é'),
+                KeyCode::Right,
+                KeyCode::Backspace,
+            ],
+        );
+        assert_eq!(form.name, "日é");
+    }
+}
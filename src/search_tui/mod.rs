@@ -5,15 +5,20 @@ use crossterm::{
 };
 use std::{
     error::Error,
-    io::{self, Stdout},
+    fs::OpenOptions,
+    io::{self, Write},
 };
 use tui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
 
-use crate::paths::{PathItem, PathItems};
+use crate::{
+    paths::{PathItem, PathItems, SearchOptions},
+    settings::StartMode,
+};
 
+mod completion;
 mod event_handler;
 mod tui_state;
 mod tui_ui;
@@ -21,13 +26,20 @@ use event_handler::handle_event;
 use tui_state::TuiState;
 use tui_ui::ui;
 
-type CrossTerminal = Terminal<CrosstermBackend<Stdout>>;
+type CrossTerminal = Terminal<CrosstermBackend<Box<dyn Write>>>;
+
+/// Setup the terminal for drawing the TUI.
+/// When `on_tty` is true, TUI is drawn on /dev/tty so stdout is free for the program output
+fn setup_terminal(on_tty: bool) -> Result<CrossTerminal, Box<dyn Error>> {
+    let mut output: Box<dyn Write> = if on_tty {
+        Box::new(OpenOptions::new().write(true).open("/dev/tty")?)
+    } else {
+        Box::new(io::stdout())
+    };
 
-fn setup_terminal() -> Result<CrossTerminal, Box<dyn Error>> {
     enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
+    execute!(output, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(output);
     let terminal = Terminal::new(backend)?;
     Ok(terminal)
 }
@@ -47,18 +59,16 @@ fn restore_terminal(terminal: &mut CrossTerminal) -> Result<(), Box<dyn Error>>
 pub fn run_edit_tui(
     items: &PathItems,
     input: &[String],
+    search: SearchOptions,
+    start_mode: StartMode,
 ) -> Result<Option<PathItems>, Box<dyn Error>> {
-    let mut terminal = setup_terminal()?;
+    let mut terminal = setup_terminal(false)?;
 
-    let mut app = TuiState::new(items, input, true);
+    let mut app = TuiState::new(items, input, true, search, start_mode);
     let res = run_app(&mut terminal, &mut app);
 
     restore_terminal(&mut terminal)?;
 
-    if let Err(ref err) = res {
-        eprintln!("{:?}", err)
-    }
-
     if let Some(path) = res? {
         Ok(path.edited_items())
     } else {
@@ -66,21 +76,22 @@ pub fn run_edit_tui(
     }
 }
 
+/// Return the selected PathItem.
+/// See [`setup_terminal`] for `on_tty`
 pub fn run_select_tui(
     items: &PathItems,
     input: &[String],
+    on_tty: bool,
+    search: SearchOptions,
+    start_mode: StartMode,
 ) -> Result<Option<PathItem>, Box<dyn Error>> {
-    let mut terminal = setup_terminal()?;
+    let mut terminal = setup_terminal(on_tty)?;
 
-    let mut app = TuiState::new(items, input, false);
+    let mut app = TuiState::new(items, input, false, search, start_mode);
     let res = run_app(&mut terminal, &mut app);
 
     restore_terminal(&mut terminal)?;
 
-    if let Err(ref err) = res {
-        eprintln!("{:?}", err)
-    }
-
     if let Some(path) = res? {
         Ok(path.selected_path.cloned())
     } else {
@@ -101,7 +112,7 @@ fn run_app<'a, B: Backend>(
             return Ok(None);
         }
 
-        if state.selected_path.is_some() {
+        if state.selected_path.is_some() || state.confirmed {
             return Ok(Some(state));
         }
     }
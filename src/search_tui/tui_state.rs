@@ -1,14 +1,128 @@
 use std::collections::HashMap;
 
-use crate::paths::{PathItem, PathItems};
+use crate::{
+    paths::{PathItem, PathItems, PathMatch, SearchOptions},
+    settings::StartMode,
+};
 
+use super::completion::complete_path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PathEditCommand {
     Delete,
+    Rename(String),
+    Describe(String),
+    Move(String),
+}
+
+impl PathEditCommand {
+    /// Commands of the same kind replace each other
+    fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// Field of a PathItem that can be edited in the TUI
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EditField {
+    Name,
+    Description,
+    #[default]
+    Path,
+}
+
+impl EditField {
+    pub fn title(&self) -> &'static str {
+        match self {
+            EditField::Name => "Name",
+            EditField::Description => "Description",
+            EditField::Path => "Path",
+        }
+    }
+
+    /// Order of the fields in the add form
+    pub const FORM_ORDER: [EditField; 3] =
+        [EditField::Path, EditField::Name, EditField::Description];
+}
+
+/// Form for adding a new PathItem
+#[derive(Default)]
+pub struct AddForm {
+    pub path: String,
+    pub name: String,
+    pub description: String,
+    /// Field that has the focus
+    pub field: EditField,
+    /// Position of cursor in the focused field
+    pub cursor: u16,
+}
+
+impl AddForm {
+    pub fn value(&self, field: EditField) -> &str {
+        match field {
+            EditField::Path => &self.path,
+            EditField::Name => &self.name,
+            EditField::Description => &self.description,
+        }
+    }
+
+    /// Focused field and its cursor for editing
+    pub fn input_mut(&mut self) -> (&mut String, &mut u16) {
+        let value = match self.field {
+            EditField::Path => &mut self.path,
+            EditField::Name => &mut self.name,
+            EditField::Description => &mut self.description,
+        };
+        (value, &mut self.cursor)
+    }
+
+    /// Move focus `step` fields forward, wrapping around
+    pub fn move_focus(&mut self, step: isize) {
+        let order = EditField::FORM_ORDER;
+        let idx = order
+            .iter()
+            .position(|f| *f == self.field)
+            .unwrap_or_default();
+        let idx = (idx as isize + step).rem_euclid(order.len() as isize) as usize;
+        self.field = order[idx];
+        self.cursor = self.value(self.field).chars().count() as u16;
+    }
+
+    pub fn is_last_field(&self) -> bool {
+        EditField::FORM_ORDER.last() == Some(&self.field)
+    }
+
+    /// Complete the path field against the filesystem
+    pub fn complete_path(&mut self) {
+        if let Some(path) = complete_path(&self.path) {
+            self.path = path;
+            self.cursor = self.path.chars().count() as u16;
+        }
+    }
+}
+
+/// Staged edits at one point of the edit history
+#[derive(Clone)]
+struct EditSnapshot<'a> {
+    edits: HashMap<&'a PathItem, Vec<PathEditCommand>>,
+    added: Vec<PathItem>,
+}
+
+/// Pending change shown in the review screen
+pub enum PendingChange<'a> {
+    Edit(&'a PathItem, &'a PathEditCommand),
+    Add(&'a PathItem),
 }
 
 pub enum InputMode {
     Select,
     Search,
+    /// Reviewing pending changes before saving them
+    Review,
+    /// Editing the field of the highlighted item in a popup
+    Edit(EditField),
+    /// Filling the form for a new PathItem
+    Add,
 }
 
 /// App holds the state of the application
@@ -22,31 +136,65 @@ pub struct TuiState<'a> {
     /// Index of selected PathItem. In filtered list, not in the orignal full list
     pub selected: usize,
     pub highlighted: Option<&'a PathItem>,
-    pub filtered: Vec<&'a PathItem>,
+    pub filtered: Vec<PathMatch<'a>>,
     pub items: &'a PathItems,
+    pub search: SearchOptions,
     pub quit: bool,
     pub selected_path: Option<&'a PathItem>,
     pub edit_mode: bool,
-    pub edits: HashMap<&'a PathItem, PathEditCommand>,
+    pub edits: HashMap<&'a PathItem, Vec<PathEditCommand>>,
+    /// Current value of the edit popup input box
+    pub edit_input: String,
+    /// Position of cursor in the edit popup
+    pub edit_cursor: u16,
+    /// Error message shown to the user, like an invalid path
+    pub message: Option<String>,
+    pub add_form: AddForm,
+    /// New PathItems added in the TUI
+    pub added: Vec<PathItem>,
+    /// Staged edits before each edit operation, latest last
+    undo_history: Vec<EditSnapshot<'a>>,
+    /// Staged edits that have been undone, latest last
+    redo_history: Vec<EditSnapshot<'a>>,
+    /// User has confirmed the pending changes
+    pub confirmed: bool,
 }
 
 impl<'a> TuiState<'a> {
-    pub fn new(items: &'a PathItems, input: &[String], edit_mode: bool) -> Self {
+    pub fn new(
+        items: &'a PathItems,
+        input: &[String],
+        edit_mode: bool,
+        search: SearchOptions,
+        start_mode: StartMode,
+    ) -> Self {
         let input = input.join(" ");
 
         let mut state = Self {
             items,
             edit_mode,
             // Empty string in a filter just copies everything
-            filtered: items.filter(&input),
+            filtered: items.search(&input, search),
+            search,
             cursor: input.chars().count() as u16,
             input,
-            input_mode: InputMode::Search,
+            input_mode: match start_mode {
+                StartMode::Search => InputMode::Search,
+                StartMode::Select => InputMode::Select,
+            },
             quit: false,
             selected: 0,
             selected_path: None,
             highlighted: None,
             edits: HashMap::new(),
+            edit_input: String::new(),
+            edit_cursor: 0,
+            message: None,
+            add_form: AddForm::default(),
+            added: Vec::new(),
+            undo_history: Vec::new(),
+            redo_history: Vec::new(),
+            confirmed: false,
         };
 
         state.set_highlighted();
@@ -63,13 +211,19 @@ impl<'a> TuiState<'a> {
 
         self.highlighted = if !self.filtered.is_empty() {
             // Copies reference (pointer) not the struct itself!
-            self.filtered.get(self.selected).copied()
+            self.filtered.get(self.selected).map(|found| found.item)
         } else {
             None
         }
     }
 
-    /// Set command to the currently highlighted item
+    /// Search match of the highlighted item
+    pub fn highlighted_match(&self) -> Option<&PathMatch<'a>> {
+        self.filtered.get(self.selected)
+    }
+
+    /// Set command to the currently highlighted item.
+    /// Delete is toggled and other commands replace the earlier command of the same kind
     pub fn set_path_command(&mut self, cmd: PathEditCommand) {
         let highlighted = if let Some(item) = self.highlighted {
             item
@@ -77,35 +231,510 @@ impl<'a> TuiState<'a> {
             return;
         };
 
-        match cmd {
-            PathEditCommand::Delete => {
-                if self.edits.contains_key(highlighted) {
-                    self.edits.remove(highlighted);
-                } else {
-                    self.edits.insert(highlighted, cmd);
+        let before = self.snapshot();
+        let cmds = self.edits.entry(highlighted).or_default();
+        let old_len = cmds.len();
+        cmds.retain(|old| !old.same_kind(&cmd));
+
+        let unchanged = match &cmd {
+            PathEditCommand::Delete => old_len != cmds.len(),
+            PathEditCommand::Rename(name) => *name == highlighted.name,
+            PathEditCommand::Describe(description) => *description == highlighted.description,
+            PathEditCommand::Move(path) => *path == highlighted.full_path,
+        };
+
+        if !unchanged {
+            cmds.push(cmd);
+        }
+
+        if cmds.is_empty() {
+            self.edits.remove(highlighted);
+        }
+
+        if before.edits != self.edits {
+            self.push_history(before);
+        }
+    }
+
+    fn snapshot(&self) -> EditSnapshot<'a> {
+        EditSnapshot {
+            edits: self.edits.clone(),
+            added: self.added.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: EditSnapshot<'a>) {
+        self.edits = snapshot.edits;
+        self.added = snapshot.added;
+    }
+
+    /// Save the state `before` an edit operation so it can be undone
+    fn push_history(&mut self, before: EditSnapshot<'a>) {
+        self.undo_history.push(before);
+        self.redo_history.clear();
+    }
+
+    /// Undo the latest edit operation
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo_history.pop() {
+            self.redo_history.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /// Redo the latest undone edit operation
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.redo_history.pop() {
+            self.undo_history.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.edits.is_empty() || !self.added.is_empty()
+    }
+
+    /// Every staged change, ordered by path
+    pub fn pending_changes(&self) -> Vec<PendingChange<'_>> {
+        let mut edited: Vec<(&&PathItem, &Vec<PathEditCommand>)> = self.edits.iter().collect();
+        edited.sort_by(|(a, _), (b, _)| a.full_path.cmp(&b.full_path));
+
+        let mut changes: Vec<PendingChange> = edited
+            .into_iter()
+            .flat_map(|(item, cmds)| cmds.iter().map(|cmd| PendingChange::Edit(item, cmd)))
+            .collect();
+        changes.extend(self.added.iter().map(PendingChange::Add));
+        changes
+    }
+
+    /// Show the review screen, or quit if there's nothing to save
+    pub fn start_review(&mut self) {
+        if self.has_changes() {
+            self.input_mode = InputMode::Review;
+        } else {
+            self.quit = true;
+        }
+    }
+
+    pub fn path_commands(&self, item: &PathItem) -> &[PathEditCommand] {
+        self.edits
+            .get(item)
+            .map(|cmds| cmds.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Current value of the `field`, including the staged edits
+    pub fn field_value<'b>(&'b self, item: &'b PathItem, field: EditField) -> &'b str {
+        for cmd in self.path_commands(item) {
+            match (cmd, field) {
+                (PathEditCommand::Rename(name), EditField::Name) => return name,
+                (PathEditCommand::Describe(description), EditField::Description) => {
+                    return description
                 }
+                (PathEditCommand::Move(path), EditField::Path) => return path,
+                _ => {}
             }
         }
+
+        match field {
+            EditField::Name => &item.name,
+            EditField::Description => &item.description,
+            EditField::Path => &item.full_path,
+        }
     }
 
-    pub fn path_command(&self, item: &PathItem) -> Option<&PathEditCommand> {
-        self.edits.get(item)
+    /// True if `path` is taken once the staged edits are applied: a saved item is
+    /// staged at it or it was added in the form. `except` is the item being moved.
+    /// Deleted items keep their paths since deleting can be toggled off
+    fn staged_path_exists(&self, path: &str, except: Option<&PathItem>) -> bool {
+        let saved = self
+            .items
+            .paths
+            .iter()
+            .filter(|item| !except.is_some_and(|except| std::ptr::eq(*item, except)))
+            .any(|item| self.field_value(item, EditField::Path) == path);
+        saved || self.added.iter().any(|item| item.full_path == path)
+    }
+
+    /// Open the edit popup for the `field` of the highlighted item
+    pub fn start_edit(&mut self, field: EditField) {
+        let highlighted = if let Some(item) = self.highlighted {
+            item
+        } else {
+            return;
+        };
+
+        self.edit_input = self.field_value(highlighted, field).to_string();
+        self.edit_cursor = self.edit_input.chars().count() as u16;
+        self.message = None;
+        self.input_mode = InputMode::Edit(field);
+    }
+
+    /// Stage the value of the edit popup. Popup stays open if the value is invalid
+    pub fn finish_edit(&mut self, field: EditField) {
+        let value = self.edit_input.trim().to_string();
+        let cmd = match field {
+            EditField::Name => PathEditCommand::Rename(value),
+            EditField::Description => PathEditCommand::Describe(value),
+            EditField::Path => match std::fs::canonicalize(&value) {
+                Ok(path) => {
+                    let Some(path) = path.to_str() else {
+                        self.message =
+                            Some(format!("Path '{}' is not valid UTF-8", path.display()));
+                        return;
+                    };
+                    let path = path.to_string();
+                    if self.staged_path_exists(&path, self.highlighted) {
+                        self.message = Some(format!("Path '{path}' is already saved"));
+                        return;
+                    }
+                    PathEditCommand::Move(path)
+                }
+                Err(_) => {
+                    self.message = Some(format!("Path '{value}' was not found"));
+                    return;
+                }
+            },
+        };
+
+        self.set_path_command(cmd);
+        self.message = None;
+        self.input_mode = InputMode::Select;
+    }
+
+    /// Open an empty form for adding a new PathItem
+    pub fn start_add(&mut self) {
+        self.add_form = AddForm::default();
+        self.message = None;
+        self.input_mode = InputMode::Add;
+    }
+
+    /// Stage the PathItem of the add form. Form stays open if the path is invalid
+    pub fn finish_add(&mut self) {
+        let form = &self.add_form;
+        let path = match std::fs::canonicalize(form.path.trim()) {
+            Ok(path) => path,
+            Err(_) => {
+                self.message = Some(format!("Path '{}' was not found", form.path.trim()));
+                return;
+            }
+        };
+        let Some(path) = path.to_str().map(String::from) else {
+            self.message = Some(format!("Path '{}' is not valid UTF-8", path.display()));
+            return;
+        };
+
+        if self.staged_path_exists(&path, None) {
+            self.message = Some(format!("Path '{path}' is already saved"));
+            return;
+        }
+
+        let before = self.snapshot();
+        self.added.push(PathItem::new(
+            form.name.trim().into(),
+            path,
+            form.description.trim().into(),
+        ));
+        self.push_history(before);
+        self.message = None;
+        self.input_mode = InputMode::Select;
     }
 
     pub fn edited_items(&self) -> Option<PathItems> {
-        if self.edits.is_empty() {
+        if !self.has_changes() {
             return None;
         }
 
         // TODO: print deleted items in verbose mode
-        // We only need to check if the path exsist in edits since we only have a delete edit
         let paths = self
             .items
             .paths
-            .clone()
-            .into_iter()
-            .filter(|path| !self.edits.contains_key(path))
+            .iter()
+            .filter_map(|path| {
+                let cmds = self.path_commands(path);
+                if cmds.contains(&PathEditCommand::Delete) {
+                    return None;
+                }
+
+                let mut path = path.clone();
+                for cmd in cmds {
+                    match cmd {
+                        PathEditCommand::Delete => {}
+                        PathEditCommand::Rename(name) => path.set_name(name.clone()),
+                        PathEditCommand::Describe(description) => {
+                            path.set_description(description.clone())
+                        }
+                        PathEditCommand::Move(full_path) => path.set_full_path(full_path.clone()),
+                    }
+                }
+                Some(path)
+            })
             .collect();
-        Some(PathItems { paths })
+
+        let mut items = PathItems::default();
+        items.paths = paths;
+        for item in &self.added {
+            items.add_path(item.clone());
+        }
+        Some(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditField, InputMode, PathEditCommand, TuiState};
+    use crate::{
+        paths::{PathItem, PathItems, SearchOptions},
+        settings::StartMode,
+    };
+
+    fn items() -> PathItems {
+        let mut items = PathItems::default();
+        items.paths = vec![
+            PathItem::new("Home".into(), "/home".into(), "Home dir".into()),
+            PathItem::new("Tmp".into(), "/tmp".into(), "Temp dir".into()),
+        ];
+        items
+    }
+
+    /// Create directories `names` in a temporary directory and return their canonical paths
+    fn create_dirs(test: &str, names: &[&str]) -> (std::path::PathBuf, Vec<String>) {
+        let root = std::env::temp_dir().join(format!("pm-{test}-{}", std::process::id()));
+        let dirs = names
+            .iter()
+            .map(|name| {
+                std::fs::create_dir_all(root.join(name)).unwrap();
+                let dir = std::fs::canonicalize(root.join(name)).unwrap();
+                dir.to_str().unwrap().to_string()
+            })
+            .collect();
+        (root, dirs)
+    }
+
+    /// Stage moving `item` to `path` in the edit popup. Returns false if it was refused
+    fn move_to<'a>(state: &mut TuiState<'a>, item: &'a PathItem, path: &str) -> bool {
+        state.highlighted = Some(item);
+        state.edit_input = path.into();
+        state.finish_edit(EditField::Path);
+        state.message.take().is_none()
+    }
+
+    #[test]
+    fn test_move_conflicts() {
+        let (root, dirs) = create_dirs("move", &["a", "b", "c", "d"]);
+        let mut items = PathItems::default();
+        items.paths = vec![
+            PathItem::new("A".into(), dirs[0].clone(), "".into()),
+            PathItem::new("B".into(), dirs[1].clone(), "".into()),
+        ];
+        let mut state = TuiState::new(
+            &items,
+            &[],
+            true,
+            SearchOptions::default(),
+            StartMode::Select,
+        );
+
+        assert!(move_to(&mut state, &items.paths[0], &dirs[2]));
+        // Another item is already moved there
+        assert!(!move_to(&mut state, &items.paths[1], &dirs[2]));
+
+        state.add_form.path = dirs[3].clone();
+        state.finish_add();
+        // Path is added in the form
+        assert!(!move_to(&mut state, &items.paths[1], &dirs[3]));
+        // Path was freed by moving A away
+        assert!(move_to(&mut state, &items.paths[1], &dirs[0]));
+
+        let edited = state.edited_items().unwrap();
+        let mut paths: Vec<&str> = edited.paths.iter().map(|p| p.full_path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, [&dirs[0], &dirs[2], &dirs[3]]);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    /// Symlink `link` in `root` to a directory whose name isn't valid UTF-8
+    #[cfg(unix)]
+    fn non_utf8_link(root: &std::path::Path, link: &str) -> String {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let target = root.join(OsStr::from_bytes(b"caf\xe9"));
+        std::fs::create_dir_all(&target).unwrap();
+        let link = root.join(link);
+        std::os::unix::fs::symlink(target, &link).unwrap();
+        link.to_str().unwrap().into()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_move_non_utf8() {
+        let (root, dirs) = create_dirs("move-utf8", &["a"]);
+        let link = non_utf8_link(&root, "link");
+        let mut items = PathItems::default();
+        items.paths = vec![PathItem::new("A".into(), dirs[0].clone(), "".into())];
+        let mut state = TuiState::new(
+            &items,
+            &[],
+            true,
+            SearchOptions::default(),
+            StartMode::Select,
+        );
+
+        state.highlighted = Some(&items.paths[0]);
+        state.edit_input = link;
+        state.input_mode = InputMode::Edit(EditField::Path);
+        state.finish_edit(EditField::Path);
+        assert!(state
+            .message
+            .as_ref()
+            .unwrap()
+            .contains("is not valid UTF-8"));
+        assert!(matches!(state.input_mode, InputMode::Edit(EditField::Path)));
+        assert!(!state.has_changes());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_non_utf8() {
+        let (root, _) = create_dirs("add-utf8", &[]);
+        let link = non_utf8_link(&root, "link");
+        let items = items();
+        let mut state = TuiState::new(
+            &items,
+            &[],
+            true,
+            SearchOptions::default(),
+            StartMode::Select,
+        );
+
+        state.start_add();
+        state.add_form.path = link;
+        state.finish_add();
+        assert!(state
+            .message
+            .as_ref()
+            .unwrap()
+            .contains("is not valid UTF-8"));
+        assert!(matches!(state.input_mode, InputMode::Add));
+        assert!(state.added.is_empty());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_add_conflicts() {
+        let (root, dirs) = create_dirs("add", &["a", "b"]);
+        let mut items = PathItems::default();
+        items.paths = vec![PathItem::new("A".into(), dirs[0].clone(), "".into())];
+        let mut state = TuiState::new(
+            &items,
+            &[],
+            true,
+            SearchOptions::default(),
+            StartMode::Select,
+        );
+        let add = |state: &mut TuiState, path: &str| {
+            state.add_form.path = path.into();
+            state.finish_add();
+            state.message.take().is_none()
+        };
+
+        assert!(move_to(&mut state, &items.paths[0], &dirs[1]));
+        // A is staged to be moved there
+        assert!(!add(&mut state, &dirs[1]));
+        assert!(add(&mut state, &dirs[0]));
+        assert!(!add(&mut state, &dirs[0]));
+        assert_eq!(state.added.len(), 1);
+        assert_eq!(state.edited_items().unwrap().paths.len(), 2);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_edit_commands() {
+        let items = items();
+        let mut state = TuiState::new(
+            &items,
+            &["home".into()],
+            true,
+            SearchOptions::default(),
+            StartMode::Search,
+        );
+
+        state.set_path_command(PathEditCommand::Rename("Old".into()));
+        state.set_path_command(PathEditCommand::Rename("New".into()));
+        state.set_path_command(PathEditCommand::Describe("Home dir".into()));
+        assert_eq!(
+            state.path_commands(&items.paths[0]),
+            [PathEditCommand::Rename("New".into())]
+        );
+
+        state.set_path_command(PathEditCommand::Delete);
+        state.set_path_command(PathEditCommand::Delete);
+        let edited = state.edited_items().unwrap();
+        assert_eq!(edited.paths.len(), 2);
+        assert_eq!(edited.paths[0].name, "New");
+        assert_eq!(edited.paths[0].description, "Home dir");
+
+        state.set_path_command(PathEditCommand::Rename("Home".into()));
+        assert!(state.edited_items().is_none());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let items = items();
+        let mut state = TuiState::new(
+            &items,
+            &["home".into()],
+            true,
+            SearchOptions::default(),
+            StartMode::Search,
+        );
+
+        state.set_path_command(PathEditCommand::Rename("New".into()));
+        // Renaming to the same name again changes nothing so it's not in the history
+        state.set_path_command(PathEditCommand::Rename("New".into()));
+        state.set_path_command(PathEditCommand::Delete);
+        assert_eq!(state.pending_changes().len(), 2);
+
+        state.undo();
+        assert_eq!(
+            state.path_commands(&items.paths[0]),
+            [PathEditCommand::Rename("New".into())]
+        );
+        state.undo();
+        assert!(!state.has_changes());
+        state.undo();
+        assert!(!state.has_changes());
+
+        state.redo();
+        state.redo();
+        assert_eq!(state.pending_changes().len(), 2);
+        state.redo();
+        assert_eq!(state.pending_changes().len(), 2);
+
+        state.undo();
+        state.set_path_command(PathEditCommand::Describe("Changed".into()));
+        state.redo();
+        assert!(!state
+            .path_commands(&items.paths[0])
+            .contains(&PathEditCommand::Delete));
+    }
+
+    #[test]
+    fn test_delete_command() {
+        let items = items();
+        let mut state = TuiState::new(
+            &items,
+            &["tmp".into()],
+            true,
+            SearchOptions::default(),
+            StartMode::Search,
+        );
+
+        state.set_path_command(PathEditCommand::Move("/var".into()));
+        state.set_path_command(PathEditCommand::Delete);
+        let edited = state.edited_items().unwrap();
+        assert_eq!(edited.paths.len(), 1);
+        assert_eq!(edited.paths[0].full_path, "/home");
     }
 }
@@ -1,28 +1,99 @@
 use tui::{
     backend::Backend,
-    layout::{Constraint, Corner, Direction, Layout},
+    layout::{Constraint, Corner, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
     Frame,
 };
 
 use crate::paths::PathItem;
 
-use super::tui_state::{InputMode, PathEditCommand, TuiState};
+use super::tui_state::{EditField, InputMode, PathEditCommand, PendingChange, TuiState};
 
 fn help_message_widget<'a>(app: &'a TuiState<'a>) -> Paragraph<'a> {
+    if let Some(message) = &app.message {
+        return Paragraph::new(message.as_str()).style(Style::default().fg(Color::Red));
+    }
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
     let (msg, style) = match app.input_mode {
+        InputMode::Select if app.edit_mode => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("q", bold),
+                Span::raw(" to exit, "),
+                Span::styled("s", bold),
+                Span::raw(" to search, "),
+                Span::styled("d", bold),
+                Span::raw(" to delete, "),
+                Span::styled("r", bold),
+                Span::raw(" to rename, "),
+                Span::styled("e", bold),
+                Span::raw(" to describe, "),
+                Span::styled("m", bold),
+                Span::raw(" to move, "),
+                Span::styled("a", bold),
+                Span::raw(" to add, "),
+                Span::styled("u", bold),
+                Span::raw("/"),
+                Span::styled("Ctrl-r", bold),
+                Span::raw(" to undo/redo, "),
+                Span::styled("Enter", bold),
+                Span::raw(" to review."),
+            ],
+            Style::default(),
+        ),
         InputMode::Select => (
             vec![
                 Span::raw("Press "),
-                Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled("q", bold),
                 Span::raw(" to exit, "),
-                Span::styled("s", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled("s", bold),
                 Span::raw(" to start searching."),
             ],
             Style::default(),
         ),
+        InputMode::Review => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("y", bold),
+                Span::raw(" or "),
+                Span::styled("Enter", bold),
+                Span::raw(" to save the changes, "),
+                Span::styled("n", bold),
+                Span::raw(" or "),
+                Span::styled("Esc", bold),
+                Span::raw(" to keep editing."),
+            ],
+            Style::default(),
+        ),
+        InputMode::Add => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("Tab", bold),
+                Span::raw(" to complete the path, "),
+                Span::styled("Up", bold),
+                Span::raw("/"),
+                Span::styled("Down", bold),
+                Span::raw(" to change field, "),
+                Span::styled("Enter", bold),
+                Span::raw(" on the last field to add, "),
+                Span::styled("Esc", bold),
+                Span::raw(" to cancel."),
+            ],
+            Style::default(),
+        ),
+        InputMode::Edit(_) => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("Enter", bold),
+                Span::raw(" to save, "),
+                Span::styled("Esc", bold),
+                Span::raw(" to cancel."),
+            ],
+            Style::default(),
+        ),
         InputMode::Search => (
             vec![
                 Span::raw("Press "),
@@ -43,7 +114,9 @@ fn help_message_widget<'a>(app: &'a TuiState<'a>) -> Paragraph<'a> {
 fn input_widget<'a>(app: &'a TuiState<'a>) -> Paragraph<'a> {
     Paragraph::new(app.input.as_ref())
         .style(match app.input_mode {
-            InputMode::Select => Style::default(),
+            InputMode::Select | InputMode::Edit(_) | InputMode::Add | InputMode::Review => {
+                Style::default()
+            }
             InputMode::Search => Style::default().fg(Color::Yellow),
         })
         .block(Block::default().borders(Borders::ALL).title("Input"))
@@ -53,23 +126,108 @@ fn cmd_delete_style() -> Style {
     Style::default().bg(Color::Red)
 }
 
+fn cmd_edit_style() -> Style {
+    Style::default().fg(Color::Yellow)
+}
+
+fn cmd_move_style() -> Style {
+    Style::default().fg(Color::Cyan)
+}
+
+fn cmd_add_style() -> Style {
+    Style::default().fg(Color::Green)
+}
+
 fn paths_view_widget_style<'a>(app: &'a TuiState<'a>, item: &PathItem) -> Style {
-    if let Some(cmd) = app.path_command(item) {
-        return match cmd {
-            PathEditCommand::Delete => cmd_delete_style(),
-        };
+    let cmds = app.path_commands(item);
+    if cmds.contains(&PathEditCommand::Delete) {
+        return cmd_delete_style();
+    }
+
+    // Moving the path is the most visible change so it wins over other edits
+    if cmds
+        .iter()
+        .any(|cmd| matches!(cmd, PathEditCommand::Move(_)))
+    {
+        return cmd_move_style();
+    }
+
+    if !cmds.is_empty() {
+        return cmd_edit_style();
+    }
+
+    Style::default()
+}
+
+/// Style for the value of an Info panel field, highlighting staged edits
+fn field_style<'a>(app: &'a TuiState<'a>, item: &PathItem, field: EditField) -> Style {
+    let original = match field {
+        EditField::Name => &item.name,
+        EditField::Description => &item.description,
+        EditField::Path => &item.full_path,
+    };
+
+    if app.field_value(item, field) != original {
+        cmd_edit_style()
+    } else {
+        Style::default()
     }
+}
 
+fn matched_char_style() -> Style {
     Style::default()
+        .fg(Color::LightBlue)
+        .add_modifier(Modifier::BOLD)
+}
+
+/// Split `text` into spans where characters in `positions` are highlighted
+fn matched_spans<'a>(text: &'a str, positions: &[usize]) -> Vec<Span<'a>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut matched = false;
+    for (idx, (byte_idx, _)) in text.char_indices().enumerate() {
+        let is_match = positions.binary_search(&idx).is_ok();
+        if is_match != matched {
+            if byte_idx > start {
+                spans.push(matched_span(&text[start..byte_idx], matched));
+            }
+            start = byte_idx;
+            matched = is_match;
+        }
+    }
+    if start < text.len() {
+        spans.push(matched_span(&text[start..], matched));
+    }
+    spans
+}
+
+fn matched_span(text: &str, matched: bool) -> Span<'_> {
+    if matched {
+        Span::styled(text, matched_char_style())
+    } else {
+        Span::raw(text)
+    }
 }
 
 fn paths_view_widget<'a>(app: &'a TuiState<'a>) -> List<'a> {
-    let paths: Vec<ListItem> = app
+    let mut paths: Vec<ListItem> = app
         .filtered
         .iter()
         .enumerate()
-        .map(|(i, m)| {
-            let content = vec![Spans::from(Span::raw(&m.full_path))];
+        .map(|(i, found)| {
+            let m = found.item;
+            let new_path = app.field_value(m, EditField::Path);
+            let content = if new_path != m.full_path {
+                vec![Spans::from(Span::raw(format!(
+                    "{} -> {new_path}",
+                    m.full_path
+                )))]
+            } else {
+                vec![Spans::from(matched_spans(
+                    &m.full_path,
+                    &found.path_positions,
+                ))]
+            };
             let style = paths_view_widget_style(app, m);
             if i == app.selected {
                 ListItem::new(content).style(style.add_modifier(Modifier::REVERSED))
@@ -79,6 +237,12 @@ fn paths_view_widget<'a>(app: &'a TuiState<'a>) -> List<'a> {
         })
         .collect();
 
+    // New paths are shown at the top of the list until they are saved
+    paths.extend(app.added.iter().map(|item| {
+        ListItem::new(Spans::from(Span::raw(format!("+ {}", item.full_path))))
+            .style(cmd_add_style())
+    }));
+
     List::new(paths)
         .start_corner(Corner::BottomLeft)
         .block(Block::default().borders(Borders::ALL).title("Paths"))
@@ -91,23 +255,145 @@ fn path_description_widget<'a>(app: &'a TuiState<'a>) -> Paragraph<'a> {
 
     let highlighted = app.highlighted.unwrap();
 
-    let name = Spans::from(vec![
-        Span::styled("Name ", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(&highlighted.name),
-    ]);
+    let name_value = app.field_value(highlighted, EditField::Name);
+    let mut name = vec![Span::styled(
+        "Name ",
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    match app.highlighted_match() {
+        // Matched characters are only known for the saved name, not for a staged one
+        Some(found) if name_value == highlighted.name => {
+            name.extend(matched_spans(name_value, &found.name_positions))
+        }
+        _ => name.push(Span::styled(
+            name_value,
+            field_style(app, highlighted, EditField::Name),
+        )),
+    }
+    let name = Spans::from(name);
+
+    let description_value = app.field_value(highlighted, EditField::Description);
+    let mut description = vec![Span::styled(
+        "Description ",
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    match app.highlighted_match() {
+        Some(found) if description_value == highlighted.description => description.extend(
+            matched_spans(description_value, &found.description_positions),
+        ),
+        _ => description.push(Span::styled(
+            description_value,
+            field_style(app, highlighted, EditField::Description),
+        )),
+    }
+    let description = Spans::from(description);
 
-    let description = Spans::from(vec![
-        Span::styled(
-            "Description ",
-            Style::default().add_modifier(Modifier::BOLD),
+    let tags = Spans::from(vec![
+        Span::styled("Tags ", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(
+            highlighted
+                .tags
+                .iter()
+                .map(|tag| format!("#{tag}"))
+                .collect::<Vec<String>>()
+                .join(" "),
         ),
-        Span::raw(&highlighted.description),
     ]);
 
-    Paragraph::new(vec![name, description])
+    Paragraph::new(vec![name, description, tags])
         .block(Block::default().borders(Borders::all()).title("Info"))
 }
 
+/// Rectangle of `width` percent of `area` and `height` rows in the middle of it
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = area.width * width / 100;
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn edit_popup_widget<'a>(app: &'a TuiState<'a>, field: EditField) -> Paragraph<'a> {
+    Paragraph::new(app.edit_input.as_ref())
+        .style(Style::default().fg(Color::Yellow))
+        .block(Block::default().borders(Borders::ALL).title(field.title()))
+}
+
+fn review_widget<'a>(app: &'a TuiState<'a>) -> List<'a> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let changes: Vec<ListItem> = app
+        .pending_changes()
+        .into_iter()
+        .map(|change| {
+            let (action, style, text) = match change {
+                PendingChange::Edit(item, PathEditCommand::Delete) => {
+                    ("Delete", cmd_delete_style(), item.full_path.clone())
+                }
+                PendingChange::Edit(item, PathEditCommand::Rename(name)) => (
+                    "Rename",
+                    cmd_edit_style(),
+                    format!("{}: '{}' -> '{name}'", item.full_path, item.name),
+                ),
+                PendingChange::Edit(item, PathEditCommand::Describe(description)) => (
+                    "Describe",
+                    cmd_edit_style(),
+                    format!("{}: '{description}'", item.full_path),
+                ),
+                PendingChange::Edit(item, PathEditCommand::Move(path)) => (
+                    "Move",
+                    cmd_move_style(),
+                    format!("{} -> {path}", item.full_path),
+                ),
+                PendingChange::Add(item) => (
+                    "Add",
+                    cmd_add_style(),
+                    format!("{} '{}'", item.full_path, item.name),
+                ),
+            };
+
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{action:9}"), style.patch(bold)),
+                Span::raw(text),
+            ]))
+        })
+        .collect();
+
+    List::new(changes).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Review changes"),
+    )
+}
+
+/// Width of the field titles in the add form, including the padding
+const ADD_FORM_LABEL_WIDTH: usize = "Description ".len();
+
+fn add_form_widget<'a>(app: &'a TuiState<'a>) -> Paragraph<'a> {
+    let form = &app.add_form;
+    let lines: Vec<Spans> = EditField::FORM_ORDER
+        .iter()
+        .map(|field| {
+            let style = if *field == form.field {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Spans::from(vec![
+                Span::styled(
+                    format!("{:ADD_FORM_LABEL_WIDTH$}", field.title()),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(form.value(*field), style),
+            ])
+        })
+        .collect();
+
+    Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Add path"))
+}
+
 pub fn ui<'a, B: Backend>(f: &mut Frame<B>, app: &'a TuiState<'a>) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -117,7 +403,7 @@ pub fn ui<'a, B: Backend>(f: &mut Frame<B>, app: &'a TuiState<'a>) {
                 // Input help message
                 Constraint::Length(1),
                 // Path description
-                Constraint::Length(4),
+                Constraint::Length(5),
                 // List of paths
                 Constraint::Min(1),
                 // Input
@@ -138,11 +424,45 @@ pub fn ui<'a, B: Backend>(f: &mut Frame<B>, app: &'a TuiState<'a>) {
     let paths = paths_view_widget(app);
     f.render_widget(paths, chunks[2]);
 
+    let input = input_widget(app);
+    f.render_widget(input, chunks[3]);
+
     match app.input_mode {
         InputMode::Select =>
             // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
             {}
 
+        InputMode::Review => {
+            let rows = app.pending_changes().len() as u16 + 2;
+            let area = centered_rect(80, rows, f.size());
+            f.render_widget(Clear, area);
+            f.render_widget(review_widget(app), area);
+        }
+
+        InputMode::Add => {
+            let area = centered_rect(60, 5, f.size());
+            f.render_widget(Clear, area);
+            f.render_widget(add_form_widget(app), area);
+
+            let form = &app.add_form;
+            // Form rows are in FORM_ORDER, one row per field
+            let row = EditField::FORM_ORDER
+                .iter()
+                .position(|field| *field == form.field)
+                .unwrap_or_default() as u16;
+            f.set_cursor(
+                area.x + ADD_FORM_LABEL_WIDTH as u16 + form.cursor + 1,
+                area.y + row + 1,
+            );
+        }
+
+        InputMode::Edit(field) => {
+            let area = centered_rect(60, 3, f.size());
+            f.render_widget(Clear, area);
+            f.render_widget(edit_popup_widget(app, field), area);
+            f.set_cursor(area.x + app.edit_cursor + 1, area.y + 1);
+        }
+
         InputMode::Search => {
             // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
             f.set_cursor(
@@ -153,7 +473,4 @@ pub fn ui<'a, B: Backend>(f: &mut Frame<B>, app: &'a TuiState<'a>) {
             )
         }
     }
-
-    let input = input_widget(app);
-    f.render_widget(input, chunks[3]);
 }
@@ -0,0 +1,200 @@
+//! User settings loaded from `config.toml`. Every setting is optional and
+//! command line flags override the settings
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    paths::{SearchMode, SortOrder},
+};
+
+/// Settings file written by `config edit` when there isn't one yet
+pub const DEFAULT_SETTINGS: &str = r#"[search]
+# How searches are matched: "fuzzy" or "substring"
+mode = "fuzzy"
+# Match search words against path descriptions too
+descriptions = true
+# Order of paths when there's no search: "path", "name" or "frecency"
+sort = "path"
+
+[tui]
+# Mode the TUI starts in: "search" or "select"
+start_mode = "search"
+
+[tmux]
+# Command sent to the tmux pane. {path} is replaced with the selected path
+cd_command = "cd {path}"
+
+[storage]
+# File the paths are saved in. Defaults to $XDG_DATA_HOME/path-manager/paths.json
+# catalog = "~/paths.json"
+# How many backups of the catalog are kept. 0 disables backups
+backups = 10
+"#;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchSettings {
+    pub mode: SearchMode,
+    pub descriptions: bool,
+    pub sort: SortOrder,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::default(),
+            descriptions: true,
+            sort: SortOrder::default(),
+        }
+    }
+}
+
+/// Mode the TUI starts in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartMode {
+    /// Typing searches
+    #[default]
+    Search,
+    /// Keys move in the list and edit paths
+    Select,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TuiSettings {
+    pub start_mode: StartMode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TmuxSettings {
+    /// `{path}` is replaced with the path to cd into
+    pub cd_command: String,
+}
+
+impl Default for TmuxSettings {
+    fn default() -> Self {
+        Self {
+            cd_command: "cd {path}".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    /// Catalog file. `~` is expanded to the home directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub catalog: Option<PathBuf>,
+    /// How many backups of the catalog are kept, 0 disables backups
+    pub backups: usize,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            catalog: None,
+            backups: 10,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub search: SearchSettings,
+    pub tui: TuiSettings,
+    pub tmux: TmuxSettings,
+    pub storage: StorageSettings,
+}
+
+impl Settings {
+    /// Parse and validate settings. `file` is only used in the error messages
+    pub fn from_toml(text: &str, file: &Path) -> Result<Self> {
+        let invalid = |message: String| {
+            Error::Config(format!("Invalid settings in {}: {message}", file.display()))
+        };
+
+        let mut settings: Settings =
+            toml::from_str(text).map_err(|e| invalid(e.to_string().trim_end().into()))?;
+
+        if !settings.tmux.cd_command.contains("{path}") {
+            return Err(invalid("tmux.cd_command has to contain {path}".into()));
+        }
+
+        if let Some(catalog) = &settings.storage.catalog {
+            if catalog.as_os_str().is_empty() {
+                return Err(invalid("storage.catalog can't be empty".into()));
+            }
+            settings.storage.catalog = Some(expand_home(catalog));
+        }
+
+        Ok(settings)
+    }
+
+    /// Load the settings from `file`. Default settings are used if the file doesn't exist
+    pub fn load(file: &Path) -> Result<Self> {
+        match fs::read_to_string(file) {
+            Ok(text) => Self::from_toml(&text, file),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::io(format!("Cannot read {}", file.display()), e)),
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        // Settings only contain types that TOML can represent
+        toml::to_string_pretty(self).unwrap()
+    }
+}
+
+/// Replace a leading `~` with the home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings() {
+        let file = Path::new("config.toml");
+        let default = Settings::from_toml(DEFAULT_SETTINGS, file).unwrap();
+        assert_eq!(default.to_toml(), Settings::default().to_toml());
+        assert_eq!(
+            Settings::from_toml("", file).unwrap().to_toml(),
+            default.to_toml()
+        );
+
+        let settings = Settings::from_toml(
+            "[search]\nmode = \"substring\"\n[tui]\nstart_mode = \"select\"",
+            file,
+        )
+        .unwrap();
+        assert_eq!(settings.search.mode, SearchMode::Substring);
+        assert!(settings.search.descriptions);
+        assert_eq!(settings.tui.start_mode, StartMode::Select);
+
+        for invalid in [
+            "[search]\nmode = \"regex\"",
+            "[search]\nunknown = 1",
+            "[colors]",
+            "[tmux]\ncd_command = \"cd\"",
+            "[storage]\ncatalog = \"\"",
+        ] {
+            let error = Settings::from_toml(invalid, file).unwrap_err();
+            assert!(matches!(error, Error::Config(_)), "{invalid}");
+        }
+    }
+}
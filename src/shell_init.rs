@@ -0,0 +1,63 @@
+/// shell_init.rs contains the shell integration scripts printed by the `init` mode
+use crate::cli::Shell;
+
+/// Function name in the scripts is replaced with the name user wants
+const CMD_PLACEHOLDER: &str = "__PM_CMD__";
+
+/// Without arguments the function opens the selector TUI, otherwise it jumps to the best match
+const POSIX_SCRIPT: &str = r#"# path-manager shell integration
+__PM_CMD__() {
+    local __pm_path
+    if [ "$#" -eq 0 ]; then
+        __pm_path="$(command path-manager tui --print)"
+    else
+        __pm_path="$(command path-manager query -- "$@")"
+    fi && [ -n "$__pm_path" ] && cd -- "$__pm_path"
+}
+"#;
+
+const FISH_SCRIPT: &str = r#"# path-manager shell integration
+function __PM_CMD__
+    set -l __pm_path
+    if test (count $argv) -eq 0
+        set __pm_path (command path-manager tui --print)
+    else
+        set __pm_path (command path-manager query -- $argv)
+    end; and test -n "$__pm_path"; and cd -- $__pm_path
+end
+"#;
+
+/// Ctrl-G opens the selector TUI
+const BASH_BINDING: &str = r#"if [[ $- == *i* ]]; then
+    bind -x '"\C-g": __PM_CMD__'
+fi
+"#;
+
+const ZSH_BINDING: &str = r#"__pm_widget() {
+    __PM_CMD__ </dev/tty
+    zle reset-prompt
+}
+zle -N __pm_widget
+bindkey '^G' __pm_widget
+"#;
+
+const FISH_BINDING: &str = r#"bind \cg '__PM_CMD__; commandline -f repaint'
+"#;
+
+/// Shell script that defines `cmd` function for jumping into saved paths.
+/// Key binding for the function is added if `bind` is true
+pub fn init_script(shell: Shell, cmd: &str, bind: bool) -> String {
+    let (script, binding) = match shell {
+        Shell::Bash => (POSIX_SCRIPT, BASH_BINDING),
+        Shell::Zsh => (POSIX_SCRIPT, ZSH_BINDING),
+        Shell::Fish => (FISH_SCRIPT, FISH_BINDING),
+    };
+
+    let mut script = script.to_string();
+    if bind {
+        script.push('\n');
+        script.push_str(binding);
+    }
+
+    script.replace(CMD_PLACEHOLDER, cmd)
+}
@@ -1,6 +1,8 @@
 /// tmux.rs contains functions to interract with the current tmux instance
 use std::{marker::PhantomData, process::Command};
 
+use crate::error::{Error, Result};
+
 #[derive(Debug)]
 pub struct Uninit;
 #[derive(Debug)]
@@ -12,94 +14,118 @@ pub struct Tmux<State = Uninit> {
     pane_index: u32,
     /// #{window_index} of the tmux window we're running this program
     window_index: u32,
+    /// Command sent to the pane to change directory. `{path}` is replaced with the path
+    cd_command: String,
     state: PhantomData<State>,
 }
 
+/// Run a tmux command and return its stdout
+fn run(command: &mut Command) -> Result<String> {
+    let output = command
+        .output()
+        .map_err(|e| Error::Tmux(format!("Cannot run tmux: {e}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Tmux(format!("tmux failed: {}", stderr.trim())));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into())
+}
+
+/// Start a tmux command without waiting for it to finish
+fn spawn(command: &mut Command) -> Result<()> {
+    // tmux finishes right away and the program exits soon after, so the child
+    // isn't left as a zombie for long
+    command
+        .spawn()
+        .map_err(|e| Error::Tmux(format!("Cannot run tmux: {e}")))?;
+    Ok(())
+}
+
 impl Tmux<Uninit> {
-    pub fn new() -> Self {
+    pub fn new(cd_command: &str) -> Self {
         Self {
             pane_index: 0,
             window_index: 0,
+            cd_command: cd_command.into(),
             state: PhantomData::<Uninit>,
         }
     }
 
-    pub fn init(self) -> Tmux<Initialized> {
-        let tmux_pane = std::env::var("TMUX_PANE")
-            .expect("Couldn't find the 'TMUX_PANE' environment variable. Make sure you're in tmux");
+    pub fn init(self) -> Result<Tmux<Initialized>> {
+        let tmux_pane = std::env::var("TMUX_PANE").map_err(|_| {
+            Error::Tmux(
+                "Couldn't find the 'TMUX_PANE' environment variable. Make sure you're in tmux"
+                    .into(),
+            )
+        })?;
 
         // Get the process tmux window and pane ids
-        let output = Command::new("tmux")
+        let stdout = run(Command::new("tmux")
             .arg("display")
             .arg("-pt")
             .arg(tmux_pane)
-            .arg("#{window_index} #{pane_index}")
-            .output()
-            .unwrap()
-            .stdout;
-        let stdout = String::from_utf8(output).unwrap();
+            .arg("#{window_index} #{pane_index}"))?;
         let output = stdout.trim();
 
-        let mut ouput_split = output.split(' ');
-
-        let window_index = ouput_split.next().unwrap();
-        let window_index = window_index.parse::<u32>().unwrap();
+        let invalid = || Error::Tmux(format!("Unexpected output from tmux: '{output}'"));
+        let (window_index, pane_index) = output.split_once(' ').ok_or_else(invalid)?;
+        let window_index = window_index.parse::<u32>().map_err(|_| invalid())?;
+        let pane_index = pane_index.parse::<u32>().map_err(|_| invalid())?;
 
-        let pane_index = ouput_split.next().unwrap();
-        let pane_index = pane_index.parse::<u32>().unwrap();
-
-        Tmux::<Initialized> {
+        Ok(Tmux::<Initialized> {
             pane_index,
             window_index,
+            cd_command: self.cd_command,
             state: PhantomData::<Initialized>,
-        }
+        })
     }
 }
 
 impl Tmux<Initialized> {
-    /// Send 'cd [`path`]' command to the tmux pane
-    pub fn cd_into(&self, path: &str) {
-        Command::new("tmux")
-            .arg("send-keys")
-            .arg(format!("-t:{}.{}", self.window_index, self.pane_index))
-            .arg(format!("cd {path}"))
-            .arg("C-m")
-            .spawn()
-            .unwrap();
+    /// Send the cd command of [`path`] to the tmux pane
+    pub fn cd_into(&self, path: &str) -> Result<()> {
+        spawn(
+            Command::new("tmux")
+                .arg("send-keys")
+                .arg(format!("-t:{}.{}", self.window_index, self.pane_index))
+                .arg(self.cd_command.replace("{path}", path))
+                .arg("C-m"),
+        )?;
+        Ok(())
     }
 
-    pub fn cd_pwd(&self) {
+    /// cd into the path saved with [`Tmux::save_pwd`]. Returns the path if there was one
+    pub fn cd_pwd(&self) -> Result<Option<String>> {
+        // showenv fails when the variable isn't set
         let output = Command::new("tmux")
             .arg("showenv")
             .arg("-g")
             .arg("PATH_MANAGER_PWD")
             .output()
-            .unwrap();
+            .map_err(|e| Error::Tmux(format!("Cannot run tmux: {e}")))?;
 
         let output = if output.stderr.is_empty() && !output.stdout.is_empty() {
-            String::from_utf8(output.stdout).unwrap()
+            String::from_utf8_lossy(&output.stdout).into_owned()
         } else {
             eprintln!("Path manager pwd is not defined");
-            return;
+            return Ok(None);
         };
 
-        let path = output.split('=').last().unwrap().trim();
-        self.cd_into(path);
+        let path = output.split_once('=').map_or("", |(_, path)| path).trim();
+        self.cd_into(path)?;
+        Ok(Some(path.into()))
     }
 
-    pub fn save_pwd(&self, path: &str) {
-        let path: String = std::fs::canonicalize(path)
-            .unwrap_or_else(|_| panic!("Path '{path}' was not found"))
-            .to_str()
-            .unwrap()
-            .into();
-
-        Command::new("tmux")
-            .arg("setenv")
-            .arg("-g")
-            .arg("PATH_MANAGER_PWD")
-            .arg(path)
-            .spawn()
-            .unwrap();
+    /// Save `path` for [`Tmux::cd_pwd`]. `path` should be canonical
+    pub fn save_pwd(&self, path: &str) -> Result<()> {
+        spawn(
+            Command::new("tmux")
+                .arg("setenv")
+                .arg("-g")
+                .arg("PATH_MANAGER_PWD")
+                .arg(path),
+        )?;
+        Ok(())
     }
 }